@@ -0,0 +1,182 @@
+// sensing cost per tick: rapier sensor cones jointed to every minion (how it used to work)
+// against spatial grid with parallel cone queries (how it works now)
+// cargo bench --bench sensing
+use std::collections::HashMap;
+use std::time::Instant;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rayon::prelude::*;
+
+#[allow(dead_code)]
+#[path = "../src/spatial.rs"]
+mod spatial;
+#[allow(dead_code)]
+#[path = "../src/vision.rs"]
+mod vision;
+
+use spatial::*;
+use vision::*;
+
+const SIGHT: f32 = 400.0;
+const HALF_ANGLE: f32 = 1.3; // ~75 degrees
+const SECTORS: usize = 5;
+const WARMUP: u32 = 10;
+const TICKS: u32 = 100;
+const CONE_STEP: f32 = 0.25;
+
+#[derive(Component)]
+struct Body;
+#[derive(Component)]
+struct Eye;
+#[derive(Component)]
+struct Morsel;
+
+#[derive(Default, Resource)]
+struct Seen(usize);
+
+// same triangle fan detectors used to have, apex in origin, pointing towards +y
+fn cone_collider(range: f32, half_angle: f32) -> Collider {
+    let steps = ((half_angle * 2.0 / CONE_STEP).ceil() as usize).max(1);
+    let dir = |b: f32| Vec2::new(b.sin(), b.cos()) * range;
+    let parts = (0..steps).map(|i|{
+        let b0 = -half_angle + 2.0 * half_angle * i as f32 / steps as f32;
+        let b1 = -half_angle + 2.0 * half_angle * (i + 1) as f32 / steps as f32;
+        (Vec2::ZERO, 0.0, Collider::triangle(Vec2::ZERO, dir(b0), dir(b1)))
+    }).collect();
+    Collider::compound(parts)
+}
+
+// n minions and n/2 food on area keeping density close to default world
+fn world(n: usize, sensors: bool) -> App {
+    let mut app = App::new();
+    app
+        .add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<Mesh>()
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .insert_resource(RapierConfiguration{
+            gravity: Vec2::ZERO,
+            timestep_mode: TimestepMode::Fixed{dt: 1.0 / 60.0, substeps: 1},
+            ..default()
+        })
+        .init_resource::<Seen>()
+    ;
+    if sensors { app.add_system(rapier_sense); }
+    else { app.insert_resource(SpatialGrid::new(SIGHT, None)).add_system(grid_sense); }
+
+    fastrand::seed(7);
+    let area = (n as f32).sqrt() * 200.0;
+    let pos = || Vec3::new((fastrand::f32() - 0.5) * area, (fastrand::f32() - 0.5) * area, 0.0);
+    for _ in 0..n {
+        let transform = Transform::from_translation(pos())
+            .with_rotation(Quat::from_rotation_z(fastrand::f32() * std::f32::consts::TAU));
+        let p = app.world.spawn(Body)
+            .insert(RigidBody::Dynamic)
+            .insert(Velocity{linvel: Vec2::new(fastrand::f32() - 0.5, fastrand::f32() - 0.5) * 200.0, angvel: 0.0})
+            .insert(Damping{linear_damping: 0.98, angular_damping: 0.98})
+            .insert(Collider::ball(16.0))
+            .insert(SpatialBundle{transform, ..default()})
+            .id();
+        if sensors {
+            let eye = app.world.spawn(Eye)
+                .insert(RigidBody::Dynamic)
+                .insert(cone_collider(SIGHT, HALF_ANGLE))
+                .insert(ColliderMassProperties::Mass(0.000001))
+                .insert(Sensor)
+                .insert(SpatialBundle{transform, ..default()})
+                .insert(ImpulseJoint::new(p, FixedJointBuilder::new()))
+                .id();
+            app.world.entity_mut(p).push_children(&[eye]);
+        }
+    }
+    for _ in 0..n / 2 {
+        app.world.spawn(Morsel)
+            .insert(RigidBody::Dynamic)
+            .insert(Collider::ball(8.0))
+            .insert(SpatialBundle{transform: Transform::from_translation(pos()), ..default()});
+    }
+    app
+}
+
+// old way, walk every sensor intersection rapier found
+fn rapier_sense(
+    ctx: Res<RapierContext>,
+    q_eye: Query<&Parent, With<Eye>>,
+    q_body: Query<&Transform, Or<(With<Body>, With<Morsel>)>>,
+    q_morsel: Query<(), With<Morsel>>,
+    mut seen: ResMut<Seen>,
+    mut sight: Local<HashMap<Entity, Vec<Sector>>>,
+){
+    sight.values_mut().for_each(|s| s.iter_mut().for_each(|s| *s = Sector::default()));
+    for (a, b, hit) in ctx.intersection_pairs() {
+        if !hit { continue; }
+        let (eye, other) = if q_eye.contains(a) { (a, b) } else { (b, a) };
+        let parent = match q_eye.get(eye) { Ok(p) => p.get(), Err(_) => continue };
+        if parent == other { continue; }
+        if let (Ok(me), Ok(it)) = (q_body.get(parent), q_body.get(other)) {
+            let diff = (it.translation - me.translation).truncate();
+            let s = sight.entry(parent).or_insert_with(|| vec![Sector::default(); SECTORS]);
+            if let Some(c) = in_cone(me.local_y().truncate(), diff, HALF_ANGLE, SIGHT) {
+                let dst = diff.length() / SIGHT / 2.0;
+                if let Some(s) = sector_of(s, c) {
+                    if q_morsel.contains(other) { s.see_food(c, dst, 0.5) } else { s.see_minion(c, dst) }
+                }
+            }
+        }
+    }
+    seen.0 = sight.len();
+}
+
+// new way, everything goes into grid, every minion looks around on its own thread
+fn grid_sense(
+    mut grid: ResMut<SpatialGrid>,
+    q_body: Query<(Entity, &Transform, Option<&Body>)>,
+    mut seen: ResMut<Seen>,
+){
+    grid.rebuild(q_body.iter().map(|(entity, t, b)| GridItem{
+        entity,
+        pos: t.translation.truncate(),
+        radius: if b.is_some() { 16.0 } else { 8.0 },
+        kind: if b.is_some() { ItemKind::Minion } else { ItemKind::Food(0.5) },
+    }));
+    let bodies: Vec<(Entity, Vec2, Vec2)> = q_body.iter()
+        .filter(|b| b.2.is_some())
+        .map(|(e, t, _)| (e, t.translation.truncate(), t.local_y().truncate()))
+        .collect();
+    let sight: Vec<Vec<Sector>> = bodies.par_iter().map(|(e, pos, fwd)|{
+        let mut sight = vec![Sector::default(); SECTORS];
+        grid.within(*pos, SIGHT, |it, diff|{
+            if it.entity == *e { return; }
+            if let Some(c) = in_cone(*fwd, diff, HALF_ANGLE, SIGHT) {
+                let dst = diff.length() / SIGHT / 2.0;
+                if let Some(s) = sector_of(&mut sight, c) {
+                    match it.kind {
+                        ItemKind::Minion => s.see_minion(c, dst),
+                        ItemKind::Food(t) => s.see_food(c, dst, t),
+                    }
+                }
+            }
+        });
+        sight
+    }).collect();
+    seen.0 = sight.len();
+}
+
+fn ms_per_tick(app: &mut App) -> f64 {
+    for _ in 0..WARMUP { app.update(); }
+    let start = Instant::now();
+    for _ in 0..TICKS { app.update(); }
+    start.elapsed().as_secs_f64() * 1000.0 / TICKS as f64
+}
+
+fn main() {
+    println!("{:>8} {:>14} {:>14}", "minions", "rapier ms/tick", "grid ms/tick");
+    for n in [250, 1000, 4000] {
+        let old = ms_per_tick(&mut world(n, true));
+        let new = ms_per_tick(&mut world(n, false));
+        println!("{n:>8} {old:>14.2} {new:>14.2}");
+    }
+}
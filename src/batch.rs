@@ -0,0 +1,178 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use bevy::{prelude::*, app::AppExit};
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{Minion, Brain, SimClock, WorldConfig, headless_app, u_hp};
+use crate::cli::{Cli, prepare_out_dir};
+use crate::headless::RunLimit;
+use crate::speed::SimSystems;
+use crate::extinction::ExtinctionLog;
+
+// what `batch` command runs, read from RON (or JSON for .json files),
+// keys of overrides are config fields, nested ones joined with dots, eg "trait_costs.sight" or "food_types.0.nutrition"
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Sweep {
+    pub base: Option<String>,                          // config every run starts from, --config otherwise
+    pub seconds: Option<f32>,                          // length of every run, --seconds / --ticks otherwise
+    pub ticks: Option<u64>,
+    pub seeds: Vec<u64>,                               // every variation runs once per seed, one random seed when empty
+    pub grid: BTreeMap<String, Vec<Value>>,            // every combination of these values
+    pub variants: Vec<BTreeMap<String, Value>>,        // each grid combination runs with each of these
+}
+impl Sweep {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        if path.ends_with(".json") {
+            serde_json::from_str(&text).map_err(|e| format!("{path}: {e}"))
+        } else {
+            ron::from_str(&text).map_err(|e| format!("{path}: {e}"))
+        }
+    }
+
+    // cartesian product of grid, times variants
+    fn variations(&self) -> Vec<BTreeMap<String, Value>> {
+        let mut out = vec![BTreeMap::new()];
+        for (key, values) in &self.grid {
+            out = out.iter()
+                .flat_map(|o| values.iter().map(move |v|{
+                    let mut o = o.clone();
+                    o.insert(key.clone(), v.clone());
+                    o
+                }))
+                .collect();
+        }
+        if self.variants.is_empty() { return out; }
+        out.iter()
+            .flat_map(|o| self.variants.iter().map(move |v|{
+                let mut o = o.clone();
+                o.extend(v.iter().map(|(k, v)| (k.clone(), v.clone())));
+                o
+            }))
+            .collect()
+    }
+}
+
+// config with given fields replaced
+fn apply(config: &WorldConfig, overrides: &BTreeMap<String, Value>) -> Result<WorldConfig, String> {
+    let mut v = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    for (key, value) in overrides {
+        let mut slot = &mut v;
+        for part in key.split('.') {
+            let next = match part.parse::<usize>() {
+                Ok(i) if slot.is_array() => slot.get_mut(i),
+                _ => slot.get_mut(part),
+            };
+            slot = next.ok_or(format!("unknown config key {key}"))?;
+        }
+        *slot = value.clone();
+    }
+    let config: WorldConfig = serde_json::from_value(v).map_err(|e| e.to_string())?;
+    config.validate()?;
+    Ok(config)
+}
+
+// what one run of batch ended with
+#[derive(Debug, Clone, Default, Resource)]
+pub struct RunSummary {
+    pub survived: f32,         // simulated seconds while anyone was alive
+    pub peak_population: usize,
+    pub final_population: usize,
+    pub mean_generation: f64,  // of last living population
+    pub extinct: bool,         // population hit zero at least once
+    pub interventions: usize,  // times extinction policy kicked in
+}
+
+fn u_summary(
+    clock: Res<SimClock>,
+    mut summary: ResMut<RunSummary>,
+    q_minions: Query<&Brain, With<Minion>>,
+){
+    let n = q_minions.iter().len();
+    summary.peak_population = summary.peak_population.max(n);
+    summary.final_population = n;
+    if n > 0 {
+        summary.survived = clock.elapsed;
+        summary.mean_generation = q_minions.iter().map(|b| b.nn.generation as f64).sum::<f64>() / n as f64;
+    } else {
+        summary.extinct = true;
+    }
+}
+
+struct BatchRun {
+    name: String,
+    seed: u64,
+    overrides: BTreeMap<String, Value>,
+    config: WorldConfig,
+}
+
+fn simulate(run: &BatchRun, limit: RunLimit) -> Result<RunSummary, String> {
+    prepare_out_dir(&run.config)?;
+    let mut app = headless_app(run.config.clone(), limit, None);
+    app
+        .init_resource::<RunSummary>()
+        .add_sim_system(u_summary.after(u_hp))
+    ;
+    // driven by hand instead of runner, so it ends (at limit, or extinction with Stop policy) and leaves world for reading
+    while app.world.resource::<Events<AppExit>>().is_empty() { app.update(); }
+    let mut summary = app.world.resource::<RunSummary>().clone();
+    summary.interventions = app.world.resource::<ExtinctionLog>().interventions.len();
+    Ok(summary)
+}
+
+fn overrides_label(overrides: &BTreeMap<String, Value>) -> String {
+    overrides.iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join(" ")
+}
+
+pub fn run_batch(cli: &Cli, path: &str) -> Result<(), String> {
+    let sweep = Sweep::load(path)?;
+    let base = Cli { config: sweep.base.clone().or(cli.config.clone()), ..cli.clone() }.world_config()?;
+    let limit = match (sweep.ticks, sweep.seconds) {
+        (Some(t), _) => RunLimit::Ticks(t),
+        (None, Some(s)) => RunLimit::Seconds(s),
+        (None, None) => cli.limit.unwrap_or(RunLimit::Seconds(600.0)),
+    };
+    let seeds = if sweep.seeds.is_empty() { vec![base.seed.unwrap_or_else(|| fastrand::u64(..))] } else { sweep.seeds.clone() };
+    let out = cli.out_dir.clone().unwrap_or_else(|| "batch".to_string());
+
+    let mut runs = vec![];
+    for overrides in sweep.variations() {
+        for seed in &seeds {
+            let name = format!("run_{:03}", runs.len());
+            let mut config = apply(&base, &overrides).map_err(|e| format!("{}: {e}", overrides_label(&overrides)))?;
+            config.seed = Some(*seed);
+            config.out_dir = Some(Path::new(&out).join(&name).to_string_lossy().into_owned());
+            runs.push(BatchRun { name, seed: *seed, overrides: overrides.clone(), config });
+        }
+    }
+    println!("Batch: {} runs ({} variations x {} seeds), {limit:?} each", runs.len(), runs.len() / seeds.len(), seeds.len());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs.unwrap_or(0)) // 0 picks all cores
+        .build()
+        .map_err(|e| e.to_string())?;
+    let results: Vec<Result<RunSummary, String>> = pool.install(|| runs.par_iter().map(|r| simulate(r, limit)).collect());
+
+    let mut table = vec!["run,seed,survived,peak_population,final_population,mean_generation,extinct,interventions,overrides".to_string()];
+    println!("{:<8} {:>20} {:>10} {:>6} {:>6} {:>8} {:>8} {:>6}  overrides", "run", "seed", "survived", "peak", "final", "mean gen", "extinct", "refill");
+    for (run, res) in runs.iter().zip(results) {
+        match res {
+            Ok(s) => {
+                table.push(format!("{},{},{:.1},{},{},{:.2},{},{},\"{}\"",
+                    run.name, run.seed, s.survived, s.peak_population, s.final_population, s.mean_generation, s.extinct, s.interventions,
+                    overrides_label(&run.overrides).replace('"', "\"\"")));
+                println!("{:<8} {:>20} {:>10.1} {:>6} {:>6} {:>8.2} {:>8} {:>6}  {}",
+                    run.name, run.seed, s.survived, s.peak_population, s.final_population, s.mean_generation, s.extinct, s.interventions, overrides_label(&run.overrides));
+            }
+            Err(e) => println!("ERR: {} failed: {e}", run.name),
+        }
+    }
+    fs::create_dir_all(&out).map_err(|e| format!("{out}: {e}"))?;
+    let summary = Path::new(&out).join("summary.csv");
+    fs::write(&summary, table.join("\n") + "\n").map_err(|e| format!("{}: {e}", summary.display()))?;
+    println!("Summary written: {}", summary.display());
+    Ok(())
+}
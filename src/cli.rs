@@ -0,0 +1,194 @@
+use std::fs;
+
+use rusty_neat::{NN, ActFunc};
+
+use crate::config::{WorldConfig, CONFIG_PATH};
+use crate::headless::RunLimit;
+use crate::hall::HallOfFame;
+
+pub const USAGE: &str = "\
+usage: micro_cosmos [command] [options]
+
+commands:
+  run                    simulation in a window (default)
+  headless               no window, stops after --seconds or --ticks (600s when neither)
+  replay <world.dat>     window, starting from saved world
+  inspect-nn <nn.dat>    prints saved brain and exits
+  inspect-hall <hall>    lists hall of fame entries and exits
+  batch <sweep.ron>      runs every variation and seed of sweep headless, in parallel, then writes summary
+
+options:
+  --config <path>        world config, world.ron when present, defaults otherwise
+  --seed <n>             seed of every random decision
+  --seconds <s>          simulated seconds to run (headless, batch)
+  --ticks <n>            ticks to run (headless, batch)
+  --out <dir>            stats, lineage, snapshots and saved brains go here
+  --population <n>       initial minions
+  --brain <nn.dat>       first minions are mutated copies of this brain
+  --hall <path>          hall of fame file, loaded on start and saved on exit
+  --from-hall <r,r,..>   first minions are mutated copies of these hall of fame entries (0 is best)
+  --load <world.dat>     start from saved world
+  --jobs <n>             worlds simulated at once (batch), all cores by default";
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Run,
+    Headless(RunLimit),
+    Replay(String),
+    InspectNn(String),
+    InspectHall(String),
+    Batch(String),
+    Help,
+}
+
+// parsed command line, options override what config file says
+#[derive(Debug, Clone)]
+pub struct Cli {
+    pub command: Command,
+    pub config: Option<String>,
+    pub seed: Option<u64>,
+    pub out_dir: Option<String>,
+    pub population: Option<usize>,
+    pub brain: Option<String>,
+    pub hall: Option<String>,
+    pub from_hall: Vec<usize>,
+    pub load: Option<String>,
+    pub limit: Option<RunLimit>, // --ticks or --seconds, ticks win when both are given
+    pub jobs: Option<usize>,
+}
+impl Cli {
+    // `args` with binary name first, like std::env::args
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut rest = args.iter().skip(1).peekable();
+        let command = match rest.peek().map(|s| s.as_str()) {
+            Some(c) if !c.starts_with('-') => { rest.next(); c.to_string() }
+            _ => "run".to_string(),
+        };
+        let mut positional = vec![];
+        let mut cli = Cli { command: Command::Run, config: None, seed: None, out_dir: None, population: None, brain: None, hall: None, from_hall: vec![], load: None, limit: None, jobs: None };
+        let mut seconds = None;
+        let mut ticks = None;
+        let mut headless = command == "headless";
+
+        while let Some(a) = rest.next() {
+            let mut value = || rest.next().cloned().ok_or(format!("{a} expects a value"));
+            match a.as_str() {
+                "--config" => cli.config = Some(value()?),
+                "--seed" => cli.seed = Some(parse(a, &value()?)?),
+                "--seconds" => seconds = Some(parse(a, &value()?)?),
+                "--ticks" => ticks = Some(parse(a, &value()?)?),
+                "--out" => cli.out_dir = Some(value()?),
+                "--population" => cli.population = Some(parse(a, &value()?)?),
+                "--brain" => cli.brain = Some(value()?),
+                "--hall" => cli.hall = Some(value()?),
+                "--from-hall" => cli.from_hall = value()?.split(',').map(|r| parse(a, r.trim())).collect::<Result<_, _>>()?,
+                "--load" => cli.load = Some(value()?),
+                "--jobs" => cli.jobs = Some(parse(a, &value()?)?),
+                // older spelling of `headless` command
+                "--headless" => headless = true,
+                "--help" | "-h" => return Ok(Cli { command: Command::Help, ..cli }),
+                _ if a.starts_with('-') => return Err(format!("unknown option {a}")),
+                _ => positional.push(a.clone()),
+            }
+        }
+
+        cli.limit = match (ticks, seconds) {
+            (Some(t), _) => Some(RunLimit::Ticks(t)),
+            (None, Some(s)) => Some(RunLimit::Seconds(s)),
+            (None, None) => None,
+        };
+        let mut file = |what: &str| match positional.len() {
+            1 => Ok(positional.remove(0)),
+            _ => Err(format!("{command} expects single {what} file")),
+        };
+        cli.command = match command.as_str() {
+            _ if headless && command != "headless" && command != "run" => return Err("--headless works only with run".to_string()),
+            _ if headless => Command::Headless(cli.limit.unwrap_or(RunLimit::Seconds(600.0))),
+            "run" => Command::Run,
+            "replay" => Command::Replay(file("world")?),
+            "inspect-nn" => Command::InspectNn(file("brain")?),
+            "inspect-hall" => Command::InspectHall(file("hall of fame")?),
+            "batch" => Command::Batch(file("sweep")?),
+            "help" => Command::Help,
+            c => return Err(format!("unknown command {c}")),
+        };
+        if !positional.is_empty() {
+            return Err(format!("unexpected argument {}", positional[0]));
+        }
+        Ok(cli)
+    }
+
+    // config file with options of command line applied on top
+    pub fn world_config(&self) -> Result<WorldConfig, String> {
+        let mut config = match &self.config {
+            Some(p) => WorldConfig::load(p)?,
+            None if fs::metadata(CONFIG_PATH).is_ok() => WorldConfig::load(CONFIG_PATH)?,
+            None => WorldConfig::default(),
+        };
+        if self.seed.is_some() { config.seed = self.seed; }
+        if let Some(n) = self.population { config.initial_minions = n; }
+        if self.brain.is_some() { config.initial_brain = self.brain.clone(); }
+        if self.out_dir.is_some() { config.out_dir = self.out_dir.clone(); }
+        if self.hall.is_some() { config.hall_path = self.hall.clone(); }
+        if !self.from_hall.is_empty() { config.initial_hall = self.from_hall.clone(); }
+        config.validate()?;
+        Ok(config)
+    }
+
+    // saved world to start from, replay's file or --load
+    pub fn snapshot(&self) -> Option<String> {
+        match &self.command {
+            Command::Replay(p) => Some(p.clone()),
+            _ => self.load.clone(),
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, v: &str) -> Result<T, String> {
+    v.parse().map_err(|_| format!("{flag} got invalid value {v}"))
+}
+
+// out directory gets config of the run with resolved seed, so it can be repeated exactly
+pub fn prepare_out_dir(config: &WorldConfig) -> Result<(), String> {
+    let dir = match &config.out_dir { Some(d) => d, None => return Ok(()) };
+    fs::create_dir_all(dir).map_err(|e| format!("{dir}: {e}"))?;
+    let text = ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default()).map_err(|e| e.to_string())?;
+    let path = config.output(CONFIG_PATH);
+    fs::write(&path, text).map_err(|e| format!("{path}: {e}"))
+}
+
+pub fn inspect_nn(path: &str) -> Result<(), String> {
+    fs::metadata(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut nn = NN::new(0, 0);
+    nn.load(path);
+
+    let active = nn.connections.iter().filter(|c| c.active).count();
+    println!("Brain: {path}");
+    println!("Generation: {}", nn.generation);
+    println!("Nodes: {}, layers: {:?}", nn.nodes.len(), nn.layer_order.iter().map(|l| l.len()).collect::<Vec<_>>());
+    println!("Connections: {} active, {} disabled", active, nn.connections.len() - active);
+    nn.nodes.iter().enumerate().for_each(|(i, n)|{
+        let act = match n.act_func {
+            ActFunc::None => "none",
+            ActFunc::Tanh => "tanh",
+            ActFunc::ReLU => "relu",
+            ActFunc::Sigmoid => "sigmoid",
+        };
+        println!("  node {i}: bias {:.3}, {act}", n.bias);
+    });
+    nn.connections.iter().filter(|c| c.active).for_each(|c| println!("  {} -> {}: {:.3}", c.from, c.to, c.weight));
+    Ok(())
+}
+
+// ranks as --from-hall takes them, fitness as it was saved
+pub fn inspect_hall(path: &str) -> Result<(), String> {
+    let (weights, entries) = HallOfFame::read(path)?;
+    println!("Hall of fame: {path}, {} entries", entries.len());
+    println!("Fitness: {weights:?}");
+    println!("{:>4} {:>10} {:>8} {:>9} {:>6} {:>8} {:>10} {:>10}", "rank", "fitness", "age", "offspring", "kills", "food", "generation", "lineage");
+    entries.iter().enumerate().for_each(|(i, e)|{
+        println!("{:>4} {:>10.1} {:>8.1} {:>9} {:>6} {:>8.2} {:>10} {:>10}",
+            i, e.fitness, e.age, e.lifetime.offspring, e.lifetime.kills, e.lifetime.food_eaten, e.generation, e.lineage.0);
+    });
+    Ok(())
+}
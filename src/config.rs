@@ -0,0 +1,305 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use rusty_neat::NN;
+
+use crate::traits::TraitCosts;
+use crate::stats::StatsFormat;
+use crate::food::{FoodType, default_food_types};
+use crate::topology::Topology;
+use crate::extinction::ExtinctionPolicy;
+use crate::hall::{FitnessWeights, HallOfFame};
+use crate::map::WorldMap;
+
+pub const CONFIG_PATH: &str = "world.ron";
+
+// every tuning value of the simulation, missing keys in file fall back to defaults
+#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub seed: Option<u64>,          // random when not given, `--seed N` overrides it
+    pub timestep: f32,              // seconds of simulation (and physics) per tick
+
+    pub initial_minions: usize,     // `--population N` overrides it
+    pub initial_brain: Option<String>, // first minions are mutated copies of this saved brain (eg nn.dat), random without it
+    pub initial_hall: Vec<usize>,   // or of these hall of fame entries (ranks, 0 is best), taken in turns
+    pub sight_sectors: usize,       // angular slices of retina, every one adds 5 nn inputs (minion, food, food type, wall, heading)
+    pub sight_half_angle: f32,      // degrees from heading to edge of vision cone
+    pub spawn_area: f32,            // side of square where first minions appear
+    pub world_bound: f32,           // half of world's side, see topology
+    pub topology: Topology,
+    pub grid_cell: f32,             // cell size of spatial index used for sensing, about usual sight radius
+    pub map_path: Option<String>,   // walls, fertile zones and spawn areas, empty world without it
+
+    pub food_area: f32,             // side of square where plants can grow, unless their food type has own areas
+    pub food_hunger_gain: f32,      // hunger gained per point of food health eaten
+    pub food_types: Vec<FoodType>,
+    pub corpse_food: usize,         // index of food type corpses turn into, has to be meat
+    pub digestion_width: f32,       // how far diet gene can be from food's diet and still digest it well
+
+    pub initial_plants: usize,
+    pub plant_max_nutrition: f32,   // food health of fully grown plant
+    pub plant_growth: f32,          // nutrition gained per second on most fertile soil
+    pub plant_lifespan: f32,        // seconds until plant withers
+    pub plant_seed_threshold: f32,  // nutrition needed before plant drops seeds
+    pub plant_seed_rate: f32,       // seeds per second of ripe plant
+    pub plant_seed_distance: f32,   // how far from parent plant seed can fall
+    pub plant_seed_nutrition: f32,  // nutrition of freshly sprouted plant
+    pub plant_sprout_rate: f32,     // plants per second sprouting anywhere on their own
+    pub plant_cap: usize,           // hard limit of plants in world, corpses not counted
+    pub plant_cell_capacity: f32,   // plants one fertility cell holds at fertility 1.0
+    pub fertility_cell: f32,        // size of soil patches
+    pub fertility_min: f32,         // fertility of worst soil, best is 1.0
+
+    pub attack_hunger_gain: f32,    // part of dealt damage turned into hunger
+    pub attack_health_gain: f32,    // part of dealt damage turned into health
+
+    pub hunger_cap: f32,            // above it hunger overflows into health
+    pub hunger_base_drain: f32,     // per second, paid even when not moving
+    pub budding: bool,              // asexual reproduction, fallback for minions not touching anyone of own species
+    pub reproduce_threshold: f32,
+    pub reproduce_cost: f32,
+    pub mating: bool,               // touching minions of same species can have child together
+    pub mating_threshold: f32,      // both partners need at least this much hunger
+    pub mating_cost: f32,           // paid by each partner
+    pub offspring_distance: f32,
+    pub mutations: usize,           // applied to every newly spawned brain
+
+    pub species_threshold: f32,     // max compatibility distance to species representative
+    pub compat_disjoint: f32,       // weight of unmatched connections in distance
+    pub compat_weight: f32,         // weight of mean difference of matched connection weights
+
+    pub trait_mutation_rate: f32,   // chance for every body gene to mutate at birth
+    pub trait_mutation_size: f32,   // max relative change of mutated gene
+    pub trait_costs: TraitCosts,
+
+    pub corpses: bool,              // dead minions leave food behind
+    pub corpse_nutrition: f32,      // health of corpse left by default sized minion
+    pub corpse_lifespan: f32,       // seconds until uneaten corpse rots away
+
+    pub senescence_exponent: f32,   // shape of frailty curve past lifespan, 1 linear, 2 quadratic
+    pub senescence_health: f32,     // health lost per second at frailty 1 (twice the lifespan)
+    pub senescence_speed: f32,      // max force is divided by 1 + this * frailty
+    pub senescence_metabolism: f32, // movement energy is multiplied by 1 + this * frailty
+
+    pub extinction_policy: ExtinctionPolicy,
+    pub extinction_threshold: usize, // policy kicks in when population drops to this
+    pub extinction_refill: usize,    // Reseed and Inject bring population back to this
+    pub hall_size: usize,            // best genomes of the dead kept for reseeding and spawning
+    pub hall_fitness: FitnessWeights, // how hall of fame ranks them
+    pub hall_path: Option<String>,   // hall of fame is loaded from here on start and saved on exit, inside out_dir when relative
+
+    pub out_dir: Option<String>,    // relative paths of stats, lineage, snapshots and saved brains are put here
+    pub snapshot_interval: f32,     // seconds between autosaves of whole world, 0 disables
+
+    pub stats_path: Option<String>, // file for population statistics, nothing is written without it
+    pub stats_format: StatsFormat,
+    pub stats_interval: f32,        // simulated seconds between rows
+
+    pub lineage_path: Option<String>, // ancestry of every minion written here on exit, .nwk for Newick, else JSON
+}
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            timestep: 1.0/60.0,
+            initial_minions: 420,
+            initial_brain: None,
+            initial_hall: vec![],
+            sight_sectors: 5,
+            sight_half_angle: 75.0,
+            spawn_area: 4000.0,
+            world_bound: 7000.0,
+            topology: Topology::Torus,
+            grid_cell: 400.0,
+            map_path: None,
+            food_area: 4000.0,
+            food_hunger_gain: 1.5,
+            food_types: default_food_types(),
+            corpse_food: 2,
+            digestion_width: 0.7,
+            initial_plants: 512,
+            plant_max_nutrition: 1.0,
+            plant_growth: 0.02,
+            plant_lifespan: 300.0,
+            plant_seed_threshold: 0.6,
+            plant_seed_rate: 0.02,
+            plant_seed_distance: 200.0,
+            plant_seed_nutrition: 0.1,
+            plant_sprout_rate: 0.2,
+            plant_cap: 1024,
+            plant_cell_capacity: 24.0,
+            fertility_cell: 500.0,
+            fertility_min: 0.1,
+            attack_hunger_gain: 1.0/3.0,
+            attack_health_gain: 1.0/2.0,
+            hunger_cap: 2.1,
+            hunger_base_drain: 1.0/20.0,
+            budding: true,
+            reproduce_threshold: 1.5,
+            reproduce_cost: 0.5,
+            mating: true,
+            mating_threshold: 1.2,
+            mating_cost: 0.3,
+            offspring_distance: 500.0,
+            mutations: 3,
+            species_threshold: 1.0,
+            compat_disjoint: 1.0,
+            compat_weight: 0.4,
+            trait_mutation_rate: 0.2,
+            trait_mutation_size: 0.1,
+            trait_costs: TraitCosts::default(),
+            corpses: true,
+            corpse_nutrition: 0.5,
+            corpse_lifespan: 120.0,
+            senescence_exponent: 2.0,
+            senescence_health: 0.05,
+            senescence_speed: 1.0,
+            senescence_metabolism: 1.0,
+            extinction_policy: ExtinctionPolicy::Stop,
+            extinction_threshold: 0,
+            extinction_refill: 50,
+            hall_size: 20,
+            hall_fitness: FitnessWeights::default(),
+            hall_path: None,
+            out_dir: None,
+            snapshot_interval: 0.0,
+            stats_path: None,
+            stats_format: StatsFormat::Csv,
+            stats_interval: 1.0,
+            lineage_path: None,
+        }
+    }
+}
+impl WorldConfig {
+    // `--config path` picks file, otherwise world.ron is used if present, `--seed N` replaces seed
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        ron::from_str(&text).map_err(|e| format!("{path}: {e}"))
+    }
+
+    // where output file of given name goes, inside out_dir when set
+    pub fn output(&self, path: &str) -> String {
+        match &self.out_dir {
+            Some(dir) if Path::new(path).is_relative() => Path::new(dir).join(path).to_string_lossy().into_owned(),
+            _ => path.to_string(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("timestep", self.timestep),
+            ("spawn_area", self.spawn_area),
+            ("world_bound", self.world_bound),
+            ("grid_cell", self.grid_cell),
+            ("food_area", self.food_area),
+            ("plant_max_nutrition", self.plant_max_nutrition),
+            ("digestion_width", self.digestion_width),
+            ("plant_lifespan", self.plant_lifespan),
+            ("corpse_lifespan", self.corpse_lifespan),
+            ("fertility_cell", self.fertility_cell),
+            ("hunger_cap", self.hunger_cap),
+            ("reproduce_threshold", self.reproduce_threshold),
+        ];
+        if let Some((name, _)) = positive.iter().find(|p| p.1 <= 0.0 || !p.1.is_finite()) {
+            return Err(format!("{name} has to be positive"));
+        }
+        if self.sight_half_angle <= 0.0 || self.sight_half_angle > 180.0 {
+            return Err("sight_half_angle has to be in (0, 180] degrees".to_string());
+        }
+        if self.sight_sectors == 0 {
+            return Err("sight_sectors has to be at least 1".to_string());
+        }
+        if let Some(t) = self.food_types.iter().find(|t| t.diet <= 0.0 || t.protection < 0.0 || t.weight < 0.0 || t.growth < 0.0) {
+            return Err(format!("food type {} needs positive diet and non negative protection, weight and growth", t.name));
+        }
+        if self.food_types.iter().filter(|t| !t.meat).map(|t| t.weight).sum::<f32>() <= 0.0 {
+            return Err("at least one non meat food type needs positive weight".to_string());
+        }
+        if !self.food_types.get(self.corpse_food).is_some_and(|t| t.meat) {
+            return Err("corpse_food has to point to meat food type".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.fertility_min) {
+            return Err("fertility_min has to be between 0 and 1".to_string());
+        }
+        if self.plant_seed_nutrition <= 0.0 || self.plant_seed_nutrition > self.plant_max_nutrition {
+            return Err("plant_seed_nutrition has to be between 0 and plant_max_nutrition".to_string());
+        }
+        let plant_rates = [self.plant_growth, self.plant_seed_rate, self.plant_seed_distance, self.plant_sprout_rate, self.plant_cell_capacity];
+        if plant_rates.iter().any(|r| *r < 0.0) {
+            return Err("plant growth and seeding parameters can't be negative".to_string());
+        }
+        if self.reproduce_cost <= 0.0 || self.reproduce_cost >= self.reproduce_threshold {
+            return Err("reproduce_cost has to be between 0 and reproduce_threshold".to_string());
+        }
+        if self.reproduce_threshold > self.hunger_cap {
+            return Err("reproduce_threshold can't be above hunger_cap, minions would never reproduce".to_string());
+        }
+        if self.mating_cost <= 0.0 || self.mating_cost >= self.mating_threshold {
+            return Err("mating_cost has to be between 0 and mating_threshold".to_string());
+        }
+        if self.mating_threshold > self.hunger_cap {
+            return Err("mating_threshold can't be above hunger_cap, minions would never mate".to_string());
+        }
+        if !self.mating && !self.budding {
+            return Err("either mating or budding has to be enabled".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.trait_mutation_rate) {
+            return Err("trait_mutation_rate has to be between 0 and 1".to_string());
+        }
+        if !(0.0..1.0).contains(&self.trait_mutation_size) {
+            return Err("trait_mutation_size has to be between 0 and 1".to_string());
+        }
+        let senescence = [self.senescence_exponent, self.senescence_health, self.senescence_speed, self.senescence_metabolism];
+        if senescence.iter().any(|s| *s < 0.0) {
+            return Err("senescence parameters can't be negative".to_string());
+        }
+        if self.senescence_exponent <= 0.0 {
+            return Err("senescence_exponent has to be positive".to_string());
+        }
+        if self.stats_interval < 0.0 {
+            return Err("stats_interval can't be negative".to_string());
+        }
+        if let Some(p) = &self.map_path {
+            // load errors already start with the path
+            WorldMap::load(p).map_err(|e| format!("map {e}"))?;
+        }
+        if let Some(p) = &self.initial_brain {
+            if fs::metadata(p).is_err() { return Err(format!("initial_brain {p} doesn't exist")); }
+            let mut nn = NN::new(0, 0);
+            nn.load(p);
+            crate::check_nn(&nn, self.sight_sectors).map_err(|e| format!("initial_brain {p}: {e}"))?;
+        }
+        if self.extinction_policy != ExtinctionPolicy::Stop && self.extinction_refill <= self.extinction_threshold {
+            return Err("extinction_refill has to be above extinction_threshold".to_string());
+        }
+        if !self.initial_hall.is_empty() && self.hall_path.is_none() {
+            return Err("initial_hall needs hall_path to take entries from".to_string());
+        }
+        if let Some(p) = &self.hall_path {
+            let path = self.output(p);
+            if fs::metadata(&path).is_ok() {
+                let (_, entries) = HallOfFame::read(&path)?;
+                for e in &entries {
+                    crate::check_nn(&e.nn, self.sight_sectors).map_err(|e| format!("hall of fame {path}: {e}"))?;
+                }
+                // loading keeps at most hall_size of them, one per genome as they already are
+                let kept = entries.len().min(self.hall_size);
+                if let Some(r) = self.initial_hall.iter().find(|r| **r >= kept) {
+                    return Err(format!("initial_hall has rank {r}, but hall of fame {path} has only {kept} entries"));
+                }
+            } else if !self.initial_hall.is_empty() {
+                return Err(format!("initial_hall needs hall of fame, {path} doesn't exist"));
+            }
+        }
+        if self.extinction_policy == ExtinctionPolicy::Reseed && self.hall_size == 0 {
+            return Err("Reseed policy needs hall_size of at least 1".to_string());
+        }
+        if self.species_threshold <= 0.0 {
+            return Err("species_threshold has to be positive".to_string());
+        }
+        Ok(())
+    }
+}
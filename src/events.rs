@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use rusty_neat::NN;
+use serde::{Serialize, Deserialize};
+
+use crate::lineage::{Lineage, LineageId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    Starvation,
+    Predation(Option<Entity>), // killer, if anyone hit it at all
+    OldAge,
+}
+
+// sent once for every minion whose health ran out, right before it is despawned
+#[derive(Debug, Clone)]
+pub struct MinionDied {
+    pub entity: Entity,
+    pub lineage: LineageId,
+    pub cause: DeathCause,
+    pub age: f32,
+    pub generation: u64,
+    pub genome: u64, // hash of network, equal for identical genomes
+    pub lifetime: Lifetime,
+}
+
+// sent for every newly spawned minion, founders included, but not for ones restored from snapshot
+#[derive(Debug, Clone)]
+pub struct MinionBorn {
+    pub entity: Entity,
+    pub lineage: Lineage,
+    pub generation: u64,
+}
+
+// who hit minion most recently and when (simulated seconds)
+#[derive(Debug, Clone, Default, Component)]
+pub struct LastAttacker {
+    pub by: Option<Entity>,
+    pub at: f32,
+}
+
+// what minion achieved so far, hall of fame fitness is made of it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Component)]
+pub struct Lifetime {
+    pub food_eaten: f32,    // hunger gained from food
+    pub damage_dealt: f32,  // health taken from other minions
+    pub damage_taken: f32,
+    pub kills: u32,
+    pub offspring: u32,     // children budded or mated
+    pub distance: f32,      // world units moved
+    pub energy_spent: f32,  // hunger (or health, when starving) burnt on moving and upkeep
+}
+
+// FNV-1a over serialized network, stable between runs unlike std hasher
+pub fn genome_hash(nn: &NN) -> u64 {
+    bincode::serialize(nn).unwrap_or_default().iter()
+        .fold(0xcbf29ce484222325, |h, b| (h ^ *b as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,104 @@
+use bevy::{prelude::*, app::AppExit};
+use serde::{Serialize, Deserialize};
+
+use crate::{Minion, Brain, SimClock, SimRng, WorldConfig, spawn_founder, u_clock, u_minion};
+use crate::traits::Traits;
+use crate::map::WorldMap;
+use crate::lineage::LineageStore;
+use crate::hall::HallOfFame;
+use crate::events::MinionBorn;
+use crate::headless::RunLimit;
+use crate::speed::{SimSpeed, SimSystems};
+use crate::stats::StatsCounters;
+
+// what happens when population drops to `extinction_threshold`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ExtinctionPolicy {
+    // report it, headless run ends, window pauses
+    #[default]
+    Stop,
+    // population is brought back to `extinction_refill` with mutated copies of hall of fame entries, best ones most
+    Reseed,
+    // same, but with fresh random minions
+    Inject,
+}
+
+#[derive(Debug, Clone)]
+pub struct Intervention {
+    pub time: f32,
+    pub population: usize,
+    pub policy: ExtinctionPolicy,
+    pub added: usize,
+}
+
+// every time policy kicked in
+#[derive(Debug, Default, Resource)]
+pub struct ExtinctionLog {
+    pub interventions: Vec<Intervention>,
+    stopped: bool, // Stop reports only once, even when paused window is resumed
+}
+
+// runs at start of tick, when everything born or killed in last one is already (de)spawned
+fn u_extinction(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    map: Res<WorldMap>,
+    hall: Res<HallOfFame>,
+    limit: Option<Res<RunLimit>>,
+    mut speed: ResMut<SimSpeed>,
+    mut rng: ResMut<SimRng>,
+    mut store: ResMut<LineageStore>,
+    mut log: ResMut<ExtinctionLog>,
+    mut counters: ResMut<StatsCounters>,
+    mut ev_born: EventWriter<MinionBorn>,
+    mut exit: EventWriter<AppExit>,
+    q_minions: Query<(), With<Minion>>,
+){
+    let population = q_minions.iter().len();
+    if population > config.extinction_threshold { return; }
+    let policy = config.extinction_policy;
+
+    let added = if policy == ExtinctionPolicy::Stop {
+        if log.stopped { return; }
+        log.stopped = true;
+        println!("Extinction: population {population} at {:.0}s (tick {}), stopping", clock.elapsed, clock.tick);
+        match limit {
+            Some(_) => exit.send(AppExit),
+            None => speed.paused = true,
+        }
+        0
+    } else {
+        let added = config.extinction_refill - population;
+        let from_hall = policy == ExtinctionPolicy::Reseed && !hall.entries.is_empty();
+        for i in 0..added {
+            let position = map.spawn_point(&config, &mut rng);
+            let (brain, traits) = if from_hall {
+                hall.entries[i % hall.entries.len()].founder(&config, &mut rng)
+            } else {
+                let brain = Brain::new(config.sight_sectors, &mut rng).offspring(config.mutations, &mut rng);
+                (brain, Traits::default().offspring(config.trait_mutation_rate, config.trait_mutation_size, &mut rng))
+            };
+            ev_born.send(spawn_founder(&mut commands, &mut store, clock.elapsed, brain, traits, position));
+        }
+        let source = match (policy, from_hall) {
+            (ExtinctionPolicy::Reseed, true) => "from hall of fame",
+            (ExtinctionPolicy::Reseed, false) => "random, hall of fame is empty",
+            _ => "random",
+        };
+        println!("Extinction: population {population} at {:.0}s (tick {}), added {added} minions ({source})", clock.elapsed, clock.tick);
+        added
+    };
+    counters.interventions += 1;
+    log.interventions.push(Intervention { time: clock.elapsed, population, policy, added });
+}
+
+pub struct ExtinctionPlugin;
+impl Plugin for ExtinctionPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<ExtinctionLog>()
+            .add_sim_system(u_extinction.after(u_clock).before(u_minion))
+        ;
+    }
+}
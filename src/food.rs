@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::map::Area;
+
+// one kind of food, plants sprout as non meat kinds and corpses turn into `corpse_food` kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FoodType {
+    pub name: String,
+    pub nutrition: f32,   // multiplies food_hunger_gain
+    pub protection: f32,  // Health.protection, lower is tougher to chew through
+    pub weight: f32,      // relative chance of sprouting plant being this kind
+    pub growth: f32,      // multiplies plant_growth
+    pub diet: f32,        // diet gene digesting it best, vision reports this value
+    pub meat: bool,       // never grows, sits in food(B) collision group
+    pub texture: String,
+    pub colour: [f32; 3], // sprite tint
+    pub areas: Vec<Area>, // plants of this kind grow only inside these, anywhere in food_area when empty
+}
+impl FoodType {
+    // whether plant of this kind can take root at `pos`
+    pub fn grows_at(&self, pos: Vec2, food_area: f32) -> bool {
+        if self.areas.is_empty() { pos.abs().max_element() <= food_area / 2.0 } else { self.areas.iter().any(|a| a.contains(pos)) }
+    }
+}
+impl Default for FoodType {
+    fn default() -> Self {
+        Self {
+            name: "grass".to_string(),
+            nutrition: 1.0,
+            protection: 1.0,
+            weight: 1.0,
+            growth: 1.0,
+            diet: 0.5,
+            meat: false,
+            texture: "Food_a.png".to_string(),
+            colour: [1.0, 1.0, 1.0],
+            areas: vec![],
+        }
+    }
+}
+
+pub fn default_food_types() -> Vec<FoodType> {
+    vec![
+        FoodType::default(),
+        FoodType { name: "nut".to_string(), nutrition: 2.0, protection: 0.4, weight: 0.3, growth: 0.5, diet: 1.0, colour: [0.8, 0.6, 0.3], ..default() },
+        FoodType { name: "meat".to_string(), nutrition: 1.5, weight: 0.0, growth: 0.0, diet: 2.0, meat: true, colour: [1.0, 0.4, 0.4], ..default() },
+    ]
+}
+
+// index into WorldConfig.food_types
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Component)]
+pub struct FoodKind(pub usize);
+
+// part of nutrition minion gets out of food, 1.0 when diet gene matches food exactly,
+// falls off with ratio between them, `width` is how forgiving guts are
+pub fn digestion(diet_gene: f32, food: &FoodType, width: f32) -> f32 {
+    let d = (diet_gene / food.diet).ln() / width;
+    (-0.5 * d * d).exp()
+}
+
+// random plant kind chosen by weight, `r` in 0..1
+pub fn pick_plant_kind(types: &[FoodType], r: f32) -> FoodKind {
+    let total: f32 = types.iter().filter(|t| !t.meat).map(|t| t.weight).sum();
+    let mut left = r * total;
+    let plants = types.iter().enumerate().filter(|(_, t)| !t.meat && t.weight > 0.0);
+    for (i, t) in plants.clone() {
+        if left < t.weight { return FoodKind(i); }
+        left -= t.weight;
+    }
+    // only reachable by rounding, last plant kind then
+    FoodKind(plants.last().map_or(0, |(i, _)| i))
+}
@@ -0,0 +1,294 @@
+use std::fs;
+
+use bevy::{prelude::*, app::AppExit};
+use rusty_neat::NN;
+use serde::{Serialize, Deserialize};
+
+use crate::{Minion, Brain, Health, Age, SimClock, SimRng, WorldConfig, reproduce, u_hp, spawn_founder};
+use crate::traits::Traits;
+use crate::map::WorldMap;
+use crate::lineage::{Lineage, LineageId, LineageStore};
+use crate::events::{genome_hash, Lifetime, MinionBorn};
+use crate::speed::SimSystems;
+
+// bumped whenever HallEntry changes, older files are refused instead of misread
+pub const HALL_VERSION: u32 = 1;
+
+// what counts as success, fitness is weighted sum of these
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FitnessWeights {
+    pub lifespan: f32,  // per second lived
+    pub offspring: f32, // per child
+    pub kills: f32,     // per minion killed
+    pub food: f32,      // per hunger gained from food
+    pub damage: f32,    // per health taken from other minions
+}
+impl Default for FitnessWeights {
+    fn default() -> Self { Self { lifespan: 1.0, offspring: 0.0, kills: 0.0, food: 0.0, damage: 0.0 } }
+}
+impl FitnessWeights {
+    pub fn fitness(&self, age: f32, life: &Lifetime) -> f32 {
+        self.lifespan * age + self.offspring * life.offspring as f32 + self.kills * life.kills as f32 + self.food * life.food_eaten
+            + self.damage * life.damage_dealt
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HallEntry {
+    pub nn: NN,
+    pub traits: Traits,
+    pub fitness: f32,
+    pub age: f32,
+    pub lifetime: Lifetime,
+    pub generation: u64,
+    pub lineage: LineageId,
+    pub genome: u64,  // genome_hash, hall keeps only best minion of each genome
+}
+impl HallEntry {
+    // mutated brain and body for a new minion
+    pub(crate) fn founder(&self, config: &WorldConfig, rng: &mut SimRng) -> (Brain, Traits) {
+        let brain = Brain::with_nn(self.nn.clone(), config.sight_sectors).offspring(config.mutations, rng);
+        let traits = self.traits.offspring(config.trait_mutation_rate, config.trait_mutation_size, rng);
+        (brain, traits)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HallFile {
+    version: u32,
+    fitness: FitnessWeights,
+    entries: Vec<HallEntry>,
+}
+
+// only version is read first, so files of other versions fail with clear message
+#[derive(Deserialize)]
+struct HallVersion {
+    version: u32,
+}
+
+// best genomes of everyone who died so far, best first, at most `hall_size` of them,
+// kept between runs in `hall_path` (JSON for .json files, binary otherwise)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Resource)]
+pub struct HallOfFame {
+    pub entries: Vec<HallEntry>,
+}
+impl HallOfFame {
+    pub fn offer(&mut self, entry: HallEntry, capacity: usize) {
+        if let Some(i) = self.entries.iter().position(|e| e.genome == entry.genome) {
+            if self.entries[i].fitness >= entry.fitness { return; }
+            self.entries.remove(i);
+        }
+        let i = self.entries.partition_point(|e| e.fitness >= entry.fitness);
+        if i >= capacity { return; }
+        self.entries.insert(i, entry);
+        self.entries.truncate(capacity);
+    }
+
+    // entries as saved, with weights they were ranked by
+    pub fn read(path: &str) -> Result<(FitnessWeights, Vec<HallEntry>), String> {
+        let bytes = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+        let json = path.ends_with(".json");
+        let version: HallVersion = if json {
+            serde_json::from_slice(&bytes).map_err(|e| format!("{path}: {e}"))?
+        } else {
+            bincode::deserialize(&bytes).map_err(|e| format!("{path}: {e}"))?
+        };
+        if version.version != HALL_VERSION {
+            return Err(format!("{path}: hall of fame version {}, expected {HALL_VERSION}", version.version));
+        }
+        let file: HallFile = if json {
+            serde_json::from_slice(&bytes).map_err(|e| format!("{path}: {e}"))?
+        } else {
+            bincode::deserialize(&bytes).map_err(|e| format!("{path}: {e}"))?
+        };
+        Ok((file.fitness, file.entries))
+    }
+
+    // loaded entries are ranked again with current weights
+    pub fn load(path: &str, weights: &FitnessWeights, capacity: usize) -> Result<Self, String> {
+        let (saved, entries) = Self::read(path)?;
+        if saved != *weights {
+            println!("Hall of fame {path} was ranked by {saved:?}, ranking it again");
+        }
+        let mut hall = Self::default();
+        entries.into_iter().for_each(|mut e|{
+            e.fitness = weights.fitness(e.age, &e.lifetime);
+            hall.offer(e, capacity);
+        });
+        Ok(hall)
+    }
+
+    pub fn save(&self, path: &str, weights: &FitnessWeights) -> Result<(), String> {
+        let file = HallFile { version: HALL_VERSION, fitness: weights.clone(), entries: self.entries.clone() };
+        let bytes = if path.ends_with(".json") {
+            serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?
+        } else {
+            bincode::serialize(&file).map_err(|e| e.to_string())?
+        };
+        fs::write(path, bytes).map_err(|e| format!("{path}: {e}"))
+    }
+}
+impl FromWorld for HallOfFame {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.resource::<WorldConfig>();
+        // same place export_hall writes to
+        match config.hall_path.as_ref().map(|p| config.output(p)) {
+            Some(path) if fs::metadata(&path).is_ok() => {
+                let hall = HallOfFame::load(&path, &config.hall_fitness, config.hall_size)
+                    .unwrap_or_else(|e| panic!("Invalid hall of fame: {e}"));
+                println!("Hall of fame: {} entries from {path}", hall.entries.len());
+                hall
+            }
+            _ => HallOfFame::default(),
+        }
+    }
+}
+
+// request to spawn `count` mutated copies of hall entry with given rank
+pub struct SpawnFromHall {
+    pub rank: usize,
+    pub count: usize,
+}
+
+// looks at minions right before u_hp takes them away
+fn u_hall(
+    config: Res<WorldConfig>,
+    mut hall: ResMut<HallOfFame>,
+    query: Query<(&Health, &Age, &Brain, &Traits, &Lineage, &Lifetime), With<Minion>>,
+){
+    query.for_each(|(health, age, brain, traits, lineage, life)|{
+        if health.health > 0.0 { return; }
+        let entry = HallEntry {
+            nn: brain.nn.clone(),
+            traits: traits.clone(),
+            fitness: config.hall_fitness.fitness(age.age, life),
+            age: age.age,
+            lifetime: life.clone(),
+            generation: brain.nn.generation as u64,
+            lineage: lineage.id,
+            genome: genome_hash(&brain.nn),
+        };
+        hall.offer(entry, config.hall_size);
+    });
+}
+
+fn spawn_from_hall(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    map: Res<WorldMap>,
+    hall: Res<HallOfFame>,
+    mut rng: ResMut<SimRng>,
+    mut store: ResMut<LineageStore>,
+    mut events: EventReader<SpawnFromHall>,
+    mut ev_born: EventWriter<MinionBorn>,
+){
+    for ev in events.iter() {
+        let entry = match hall.entries.get(ev.rank) {
+            Some(e) => e,
+            None => { println!("ERR: No hall of fame entry {}", ev.rank); continue; }
+        };
+        for _ in 0..ev.count {
+            let position = map.spawn_point(&config, &mut rng);
+            let (brain, traits) = entry.founder(&config, &mut rng);
+            ev_born.send(spawn_founder(&mut commands, &mut store, clock.elapsed, brain, traits, position));
+        }
+        println!("Spawned {} minions from hall of fame entry {} (fitness {:.1})", ev.count, ev.rank, entry.fitness);
+    }
+}
+
+fn export_hall(
+    config: Res<WorldConfig>,
+    hall: Res<HallOfFame>,
+    mut exit: EventReader<AppExit>,
+){
+    if exit.iter().next().is_none() { return; }
+    if let Some(path) = &config.hall_path {
+        let path = config.output(path);
+        match hall.save(&path, &config.hall_fitness) {
+            Ok(()) => println!("Hall of fame saved: {path}"),
+            Err(e) => println!("ERR: Hall of fame not saved: {e}"),
+        }
+    }
+}
+
+pub struct HallPlugin;
+impl Plugin for HallPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<HallOfFame>()
+            .add_event::<SpawnFromHall>()
+            .add_sim_system(u_hall.after(reproduce).before(u_hp))
+            // not in sim loop, so requests from ui are not lost while paused
+            .add_system(spawn_from_hall)
+            .add_system_to_stage(CoreStage::Last, export_hall)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(genome: u64, age: f32, kills: u32) -> HallEntry {
+        let lifetime = Lifetime { kills, ..default() };
+        HallEntry {
+            nn: NN::new(1, 1),
+            traits: Traits::default(),
+            fitness: FitnessWeights::default().fitness(age, &lifetime),
+            age,
+            lifetime,
+            generation: 0,
+            lineage: LineageId(genome),
+            genome,
+        }
+    }
+
+    fn ages(hall: &HallOfFame) -> Vec<f32> {
+        hall.entries.iter().map(|e| e.age).collect()
+    }
+
+    #[test]
+    fn offer_keeps_best_of_each_genome() {
+        let mut hall = HallOfFame::default();
+        hall.offer(entry(1, 5.0, 0), 10);
+        hall.offer(entry(1, 3.0, 0), 10);
+        assert_eq!(ages(&hall), vec![5.0]);
+        hall.offer(entry(1, 8.0, 0), 10);
+        hall.offer(entry(2, 6.0, 0), 10);
+        assert_eq!(ages(&hall), vec![8.0, 6.0]);
+    }
+
+    #[test]
+    fn full_hall_drops_worst() {
+        let mut hall = HallOfFame::default();
+        [(1, 5.0), (2, 7.0), (3, 1.0)].iter().for_each(|(g, a)| hall.offer(entry(*g, *a, 0), 2));
+        assert_eq!(ages(&hall), vec![7.0, 5.0]);
+        hall.offer(entry(4, 6.0, 0), 2);
+        assert_eq!(ages(&hall), vec![7.0, 6.0]);
+        hall.offer(entry(5, 2.0, 0), 2);
+        assert_eq!(ages(&hall), vec![7.0, 6.0]);
+    }
+
+    #[test]
+    fn load_ranks_again_and_refuses_other_versions() {
+        // binary, networks can hold NaN that JSON can't
+        let path = std::env::temp_dir().join(format!("hall_test_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut hall = HallOfFame::default();
+        hall.offer(entry(1, 10.0, 0), 10);
+        hall.offer(entry(2, 5.0, 3), 10);
+        hall.save(path, &FitnessWeights::default()).unwrap();
+
+        let kills = FitnessWeights { lifespan: 0.0, kills: 1.0, ..default() };
+        let loaded = HallOfFame::load(path, &kills, 10).unwrap();
+        assert_eq!(ages(&loaded), vec![5.0, 10.0]);
+        assert_eq!(loaded.entries[0].fitness, 3.0);
+
+        let file = HallFile { version: HALL_VERSION + 1, fitness: kills, entries: vec![] };
+        fs::write(path, bincode::serialize(&file).unwrap()).unwrap();
+        assert!(HallOfFame::load(path, &FitnessWeights::default(), 10).is_err());
+        fs::remove_file(path).ok();
+    }
+}
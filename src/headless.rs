@@ -0,0 +1,46 @@
+use bevy::{prelude::*, app::AppExit, asset::AssetPlugin, hierarchy::HierarchyPlugin, input::InputPlugin, transform::TransformPlugin};
+
+use crate::{ui::MinionAmount, SimClock};
+
+// how long headless run should last, either simulated seconds or ticks (frames),
+// `headless` command takes it from --seconds / --ticks
+#[derive(Debug, Clone, Copy, Resource)]
+pub enum RunLimit {
+    Seconds(f32),
+    Ticks(u64),
+}
+// closes app when simulated time reaches limit
+fn headless_stop(
+    clock: Res<SimClock>,
+    limit: Res<RunLimit>,
+    m_a: Res<MinionAmount>,
+    mut exit: EventWriter<AppExit>,
+){
+    let done = match *limit {
+        RunLimit::Seconds(s) => clock.elapsed >= s,
+        RunLimit::Ticks(t) => clock.tick >= t,
+    };
+    if done {
+        println!("Finished: {} ticks, {:.0}s simulated, population: {}", clock.tick, clock.elapsed, m_a.0);
+        exit.send(AppExit);
+    }
+}
+
+// bare minimum of bevy needed by simulation and rapier, without window or renderer
+pub struct HeadlessPlugin {
+    pub limit: RunLimit,
+}
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>() // rapier looks for mesh assets even in 2d
+            .add_system_to_stage(CoreStage::PostUpdate, headless_stop)
+            .insert_resource(self.limit)
+        ;
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fs};
+
+use bevy::{prelude::*, app::AppExit};
+use serde::{Serialize, Deserialize};
+
+use crate::{SimClock, WorldConfig, u_hp};
+use crate::events::{Lifetime, MinionDied};
+use crate::species::Species;
+use crate::traits::Traits;
+use crate::speed::SimSystems;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LineageId(pub u64);
+
+// stable identity of a minion, survives snapshots unlike Entity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Component)]
+pub struct Lineage{
+    pub id: LineageId,
+    pub parent: Option<LineageId>, // fitter parent, founders have none
+    pub mate: Option<LineageId>,   // second parent of sexually produced child
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineageNode{
+    pub id: LineageId,
+    pub parent: Option<LineageId>,
+    pub mate: Option<LineageId>,
+    pub founder: LineageId, // one of first minions this one descends from
+    pub born: f32,
+    pub died: Option<f32>,
+    pub generation: u64,
+    pub traits: Traits,
+    pub species: Option<u32>,
+    pub children: Vec<LineageId>,
+    pub lifetime: Option<Lifetime>, // what it achieved, once dead
+}
+
+// one branch of drawn tree, spans from birth to death (or now) in row `row`
+#[derive(Debug, Clone)]
+pub struct TreeBranch {
+    pub id: LineageId,
+    pub parent: Option<usize>, // index of parent branch in same vec
+    pub start: f32,
+    pub end: f32,
+    pub row: f32,
+    pub alive: bool,
+    pub descendant: LineageId, // some living minion of this clade
+}
+
+enum Visit {
+    Enter(LineageId, Option<usize>),
+    Exit(usize),
+}
+
+// every minion that has ever lived, kept after death so ancestry can be traced back to founders
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Resource)]
+pub struct LineageStore{
+    next_id: u64,
+    nodes: BTreeMap<LineageId, LineageNode>,
+}
+impl LineageStore {
+    pub fn birth(&mut self, parent: Option<LineageId>, mate: Option<LineageId>, born: f32, generation: u64, traits: &Traits) -> Lineage {
+        let id = LineageId(self.next_id);
+        self.next_id += 1;
+
+        let founder = parent
+            .and_then(|p| self.nodes.get_mut(&p))
+            .map_or(id, |p|{ p.children.push(id); p.founder });
+        self.nodes.insert(id, LineageNode{id, parent, mate, founder, born, died: None, generation, traits: traits.clone(), species: None, children: vec![], lifetime: None});
+        Lineage{id, parent, mate}
+    }
+
+    fn death(&mut self, id: LineageId, at: f32, lifetime: &Lifetime) {
+        if let Some(n) = self.nodes.get_mut(&id) {
+            n.died = Some(at);
+            n.lifetime = Some(lifetime.clone());
+        }
+    }
+
+    pub fn get(&self, id: LineageId) -> Option<&LineageNode> {
+        self.nodes.get(&id)
+    }
+
+    // forest in Newick format, one tree per founder, branch lengths are seconds between births
+    pub fn to_newick(&self) -> String {
+        self.nodes.values()
+            .filter(|n| n.parent.is_none())
+            .map(|n| self.subtree_newick(n.id) + ";\n")
+            .collect()
+    }
+
+    // built bottom-up without recursion, lineages can be thousands of generations deep
+    fn subtree_newick(&self, root: LineageId) -> String {
+        let mut stack = vec![(root, false)];
+        let mut done: HashMap<LineageId, String> = HashMap::new();
+
+        while let Some((id, expanded)) = stack.pop() {
+            let node = &self.nodes[&id];
+            if !expanded && !node.children.is_empty() {
+                stack.push((id, true));
+                node.children.iter().rev().for_each(|c| stack.push((*c, false)));
+                continue;
+            }
+            let born_at = node.parent.and_then(|p| self.nodes.get(&p)).map_or(node.born, |p| p.born);
+            let label = format!("{}:{:.3}", id.0, node.born - born_at);
+            let text = if node.children.is_empty() { label } else {
+                let kids: Vec<String> = node.children.iter().filter_map(|c| done.remove(c)).collect();
+                format!("({}){}", kids.join(","), label)
+            };
+            done.insert(id, text);
+        }
+        done.remove(&root).unwrap_or_default()
+    }
+
+    // ancestry of living minions only, branches that died out are left away,
+    // leaves get consecutive rows and parents sit in the middle of their children
+    pub fn living_tree(&self, living: &HashSet<LineageId>, now: f32) -> Vec<TreeBranch> {
+        let mut keep: HashSet<LineageId> = HashSet::new();
+        living.iter().for_each(|id|{
+            let mut cur = Some(*id);
+            while let Some(c) = cur {
+                if !keep.insert(c) { break; }
+                cur = self.nodes.get(&c).and_then(|n| n.parent);
+            }
+        });
+
+        let mut out: Vec<TreeBranch> = vec![];
+        let mut kids: Vec<Vec<usize>> = vec![];
+        let mut rows = 0.0;
+        let roots = self.nodes.values().filter(|n| n.parent.is_none() && keep.contains(&n.id));
+        for root in roots {
+            let mut stack = vec![Visit::Enter(root.id, None)];
+            while let Some(v) = stack.pop() {
+                match v {
+                    Visit::Enter(id, parent) => {
+                        let n = &self.nodes[&id];
+                        let idx = out.len();
+                        out.push(TreeBranch{ id, parent, start: n.born, end: n.died.unwrap_or(now), row: 0.0, alive: living.contains(&id), descendant: id });
+                        kids.push(vec![]);
+                        if let Some(p) = parent { kids[p].push(idx); }
+                        stack.push(Visit::Exit(idx));
+                        n.children.iter().rev().filter(|c| keep.contains(c)).for_each(|c| stack.push(Visit::Enter(*c, Some(idx))));
+                    }
+                    Visit::Exit(idx) => {
+                        let k = &kids[idx];
+                        if let (Some(first), Some(last)) = (k.first(), k.last()) {
+                            out[idx].row = (out[*first].row + out[*last].row) / 2.0;
+                            if !out[idx].alive { out[idx].descendant = out[*first].descendant; }
+                        } else {
+                            out[idx].row = rows;
+                            rows += 1.0;
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.nodes.values().collect::<Vec<_>>()).unwrap()
+    }
+
+    // format is picked by extension, .nwk / .newick for Newick, anything else is JSON
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = if path.ends_with(".nwk") || path.ends_with(".newick") { self.to_newick() } else { self.to_json() };
+        fs::write(path, text).map_err(|e| format!("{path}: {e}"))
+    }
+}
+
+fn u_lineage_deaths(
+    clock: Res<SimClock>,
+    mut store: ResMut<LineageStore>,
+    mut events: EventReader<MinionDied>,
+){
+    events.iter().for_each(|ev| store.death(ev.lineage, clock.elapsed, &ev.lifetime));
+}
+
+// species is assigned after birth, so it's copied into the tree once known
+fn u_lineage_species(
+    mut store: ResMut<LineageStore>,
+    query: Query<(&Lineage, &Species), Changed<Species>>,
+){
+    query.for_each(|(l, s)|{
+        if let Some(n) = store.nodes.get_mut(&l.id) { n.species = Some(s.id); }
+    });
+}
+
+fn export_lineage(
+    config: Res<WorldConfig>,
+    store: Res<LineageStore>,
+    mut exit: EventReader<AppExit>,
+){
+    if exit.iter().next().is_none() { return; }
+    if let Some(path) = &config.lineage_path {
+        let path = config.output(path);
+        match store.save(&path) {
+            Ok(()) => println!("Lineage saved: {path}"),
+            Err(e) => println!("ERR: Lineage not saved: {e}"),
+        }
+    }
+}
+
+pub struct LineagePlugin;
+impl Plugin for LineagePlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<LineageStore>()
+            // same tick as death, so recorded time doesn't depend on executor order
+            .add_sim_system(u_lineage_deaths.after(u_hp))
+            .add_sim_system(u_lineage_species)
+            .add_system_to_stage(CoreStage::Last, export_lineage)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // founder 0 with children 1 and 2, grandchild 3 of 1, and unrelated founder 4
+    fn store() -> LineageStore {
+        let t = Traits::default();
+        let mut s = LineageStore::default();
+        let root = s.birth(None, None, 0.0, 0, &t).id;
+        let a = s.birth(Some(root), None, 1.5, 1, &t).id;
+        s.birth(Some(root), Some(a), 4.0, 1, &t);
+        s.birth(Some(a), None, 2.0, 2, &t);
+        s.birth(None, None, 3.0, 0, &t);
+        s
+    }
+
+    #[test]
+    fn newick_has_tree_per_founder_with_branch_lengths_from_parent_birth() {
+        assert_eq!(store().to_newick(), "((3:0.500)1:1.500,2:4.000)0:0.000;\n4:0.000;\n");
+    }
+
+    #[test]
+    fn descendants_keep_founder_and_death() {
+        let mut s = store();
+        s.death(LineageId(3), 7.0, &Lifetime::default());
+        assert_eq!(s.get(LineageId(3)).unwrap().founder, LineageId(0));
+        assert_eq!(s.get(LineageId(2)).unwrap().mate, Some(LineageId(1)));
+        assert_eq!(s.get(LineageId(4)).unwrap().founder, LineageId(4));
+        assert_eq!(s.get(LineageId(3)).unwrap().died, Some(7.0));
+        assert_eq!(s.get(LineageId(0)).unwrap().children, vec![LineageId(1), LineageId(2)]);
+    }
+}
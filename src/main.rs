@@ -1,17 +1,58 @@
 #![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
 
-use std::f32::consts::PI;
+use std::collections::HashSet;
 
-//use rayon::prelude::*;
+use rayon::prelude::*;
 use serde::{Serialize, Deserialize};
 //use bincode::{serialize, deserialize};
 use rusty_neat::*;
-use bevy::{prelude::*, diagnostic::FrameTimeDiagnosticsPlugin};
+use bevy::{prelude::*, diagnostic::FrameTimeDiagnosticsPlugin, render::texture::DEFAULT_IMAGE_HANDLE};
 use bevy_rapier2d::prelude::*;
 use fastrand as fr;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 mod ui;
 use ui::*;
+mod headless;
+use headless::*;
+mod config;
+use config::*;
+mod snapshot;
+use snapshot::*;
+mod species;
+use species::*;
+mod traits;
+use traits::*;
+mod vision;
+use vision::*;
+mod stats;
+use stats::*;
+mod events;
+use events::*;
+mod lineage;
+use lineage::*;
+mod plants;
+use plants::*;
+mod food;
+use food::*;
+mod map;
+use map::*;
+mod topology;
+use topology::*;
+mod speed;
+use speed::*;
+mod cli;
+use cli::*;
+mod batch;
+use batch::*;
+mod hall;
+use hall::*;
+mod extinction;
+use extinction::*;
+mod spatial;
+use spatial::*;
 
 
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
@@ -23,9 +64,6 @@ struct Control;
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
 struct Cursor;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Component)]
-struct Detector;
-
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
 struct Food;
 
@@ -39,25 +77,67 @@ impl Default for BestNN{
     fn default() -> Self { Self { nn: NN::new(0, 0), age: 0.0 } }
 }
 
+// inputs: velocity, angular velocity, hp, hunger, attacked, ate, then for every sector:
+// minion distance, food distance, food type, wall distance, heading to nearest of them
+// outputs: force, torque, attack, eat
+const NN_BASE_INPUTS: usize = 6;
+const NN_SECTOR_INPUTS: usize = 5;
+const NN_OUTPUTS: usize = 4;
+
+fn nn_inputs(sectors: usize) -> usize { NN_BASE_INPUTS + NN_SECTOR_INPUTS * sectors }
+
+// saved networks have to fit retina and outputs ai reads, otherwise first tick panics
+fn check_nn(nn: &NN, sectors: usize) -> Result<(), String> {
+    let inputs = nn.layer_order.first().map_or(0, |l| l.len());
+    let outputs = nn.layer_order.last().map_or(0, |l| l.len());
+    if inputs != nn_inputs(sectors) || outputs != NN_OUTPUTS {
+        return Err(format!("network has {inputs} inputs and {outputs} outputs, {sectors} sight sectors need {} and {NN_OUTPUTS}", nn_inputs(sectors)));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
 struct Brain{
     nn: NN, 
-    sight_minions: Vec<(f32, f32)>, // angles and distances to minions in sight
-    sight_food: Vec<(f32, f32)>, // angles and distances to food in sight
-    //attacking: bool,
-    //eating: bool
-}
-impl Default for Brain {
-    fn default() -> Self {
-        let mut n = NN::new(8, 2); 
+    sight: Vec<Sector>, // retina, filled by detect and emptied by ai
+    wants_attack: bool, // decided by nn, weapon hurts minions only when set
+    wants_eat: bool, // decided by nn, weapon eats food only when set
+    attacking: bool, // weapon actually hit a minion since last think
+    eating: bool // weapon actually ate food since last think
+}
+impl Brain {
+    // fresh random network for retina with given amount of sectors,
+    // neat draws its weights from fastrand, so it's reseeded from sim rng first
+    fn new(sectors: usize, rng: &mut SimRng) -> Self {
+        rng.reseed_neat();
+        let mut n = NN::new(nn_inputs(sectors), NN_OUTPUTS); 
         n.forward(&[0.5]); 
+        Self::with_nn(n, sectors)
+    }
+
+    fn with_nn(nn: NN, sectors: usize) -> Self {
         Self { 
-        nn: n, 
-        sight_minions: vec![(0.0, f32::MAX)], 
-        sight_food: vec![(0.0, f32::MAX)], 
-        //attacking: false,
-        //eating: false
-    }}
+            nn, 
+            sight: vec![Sector::default(); sectors], 
+            wants_attack: false,
+            wants_eat: false,
+            attacking: false,
+            eating: false
+        }
+    }
+
+    // copy of network for a newborn, mutated given amount of times
+    fn offspring(&self, mutations: usize, rng: &mut SimRng) -> Self {
+        rng.reseed_neat();
+        let mut brain = Self::with_nn(self.nn.clone(), self.sight.len());
+        for _ in 0..mutations { brain.nn.mutate(); }
+        brain
+    }
+
+    // not yet mutated child of two parents, self should be the fitter one
+    fn crossed(&self, other: &Brain, rng: &mut SimRng) -> Self {
+        Self::with_nn(crossover(&self.nn, &other.nn, rng), self.sight.len())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
@@ -74,13 +154,15 @@ impl Default for Size {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
 struct Health{health: f32, protection: f32}
-impl Health {
-    fn new(hp: f32) -> Self { Self { health: hp, protection: 1.0 } }
-}
 impl Default for Health {
     fn default() -> Self {Self { health: 1.0, protection: 1.0 }}
 }
 
+// weapon is a box WEAPON_REACH in front of minion's edge, `width` to each side and WEAPON_DEPTH deep,
+// measured from edge so bigger bodies don't swallow their own weapon
+const WEAPON_REACH: f32 = 8.0;
+const WEAPON_DEPTH: f32 = 4.0;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
 struct Weapon{damage: f32, width: f32}
 impl Default for Weapon {
@@ -92,6 +174,14 @@ struct Age{age: f32, lifespan: f32}
 impl Default for Age{
     fn default() -> Self {Self{age: 0.0, lifespan: 720.0}}
 }
+impl Age {
+    // 0 until lifespan, then grows with part of lifespan lived over it, raised to exponent
+    fn frailty(&self, exponent: f32) -> f32 {
+        // checked first, 0^0 would be 1
+        if self.age <= self.lifespan { return 0.0; }
+        ((self.age - self.lifespan) / self.lifespan).powf(exponent)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Component)]
 struct Hunger{filled: f32, metabolism: f32}
@@ -99,178 +189,334 @@ impl Default for Hunger{
     fn default() -> Self {Self{filled: 1.0, metabolism: 0.02}}
 }
 
+// everything needed to spawn a minion, either fresh one or restored from snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MinionState{
+    brain: Brain,
+    health: Health,
+    hunger: Hunger,
+    age: Age,
+    sight: Sight,
+    size: Size,
+    weapon: Weapon,
+    traits: Traits,
+    lineage: Lineage,
+    lifetime: Lifetime,
+    position: [f32; 2],
+    rotation: f32,
+    linvel: [f32; 2],
+    angvel: f32,
+    species: Option<Species>, // newborns get it assigned after spawn
+}
+impl MinionState {
+    // body of a newborn is decided by its traits
+    fn new(brain: Brain, traits: Traits, lineage: Lineage, position: Vec2) -> Self {
+        let (health, hunger, age) = (Health::default(), Hunger::default(), Age::default());
+        let (sight, size, weapon) = (Sight::default(), Size::default(), Weapon::default());
+        Self {
+            brain,
+            health: Health{protection: health.protection / traits.armour, ..health},
+            hunger: Hunger{metabolism: hunger.metabolism * traits.metabolism, ..hunger},
+            age: Age{lifespan: age.lifespan * traits.lifespan, ..age},
+            sight: Sight{radius: sight.radius * traits.sight},
+            size: Size{radius: size.radius * traits.size},
+            weapon: Weapon{damage: weapon.damage * traits.damage, width: weapon.width * traits.reach},
+            traits,
+            lineage,
+            lifetime: Lifetime::default(),
+            position: position.to_array(),
+            rotation: 0.0,
+            linvel: [0.0, 0.0],
+            angvel: 0.0,
+            species: None,
+        }
+    }
+}
+
+// simulated time, unlike Time it advances by fixed step and is restored together with world
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Resource)]
+struct SimClock{elapsed: f32, dt: f32, tick: u64}
+
+// single source of randomness, every random decision of simulation goes through it
+#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+struct SimRng(ChaCha8Rng);
+impl SimRng {
+    fn new(seed: u64) -> Self { Self(ChaCha8Rng::seed_from_u64(seed)) }
+    fn f32(&mut self) -> f32 { self.0.gen() }
+    fn u64(&mut self) -> u64 { self.0.gen() }
+
+    // rusty_neat draws from fastrand's thread local generator, 
+    // so it is reseeded from ours right before every use, no matter which thread runs the system
+    fn reseed_neat(&mut self) { fr::seed(self.0.gen()); }
+}
+impl Default for SimRng {
+    fn default() -> Self { Self::new(0) }
+}
+
 
 
 fn ai( mut m_a: ResMut<MinionAmount>,
-    mut query: Query<(&mut Brain, &mut ExternalForce, &mut Transform, &mut Velocity, &Hunger, &Health), (With<Minion>, Without<Control>)>,
+    config: Res<WorldConfig>,
+    mut query: Query<(&mut Brain, &mut ExternalForce, &Transform, &mut Velocity, &Hunger, &Health, &Traits, &Age), (With<Minion>, Without<Control>)>,
 ) {
     m_a.0 = query.iter().len();
-    query.par_for_each_mut(16, |(mut brain, mut force, mut transform, velocity, hunger, hp)| {
-        let mut s_minions = (0_f32, f32::MAX);
-        brain.sight_minions.iter().for_each(|s|{
-            if s.1 < s_minions.1 { s_minions = *s}
-        });
-        brain.sight_minions.clear();
-        //let atc = brain.attacking as i32 as f64;
-        //brain.attacking = false;
+    query.par_for_each_mut(16, |(mut brain, mut force, transform, velocity, hunger, hp, traits, age)| {
+        let atc = brain.attacking as i32 as f64;
+        brain.attacking = false;
+        let eat = brain.eating as i32 as f64;
+        brain.eating = false;
 
-        let mut s_foods = (0_f32, f32::MAX);
-        brain.sight_food.iter().for_each(|s|{
-            if s.1 < s_foods.1 { s_foods = *s}
-        });
-        brain.sight_food.clear();
-        //let eat = brain.eating as i32 as f64;
-        //brain.eating = false;
-
-
-        let out = brain.nn.forward(&[
+        let mut input = vec![
             velocity.linvel.length() as f64,
             velocity.angvel as f64,
             hp.health as f64,
             hunger.filled as f64,
-            s_minions.0 as f64,
-            1.0/s_minions.1 as f64,
-            s_foods.0 as f64,
-            1.0/s_foods.1 as f64,
-            ]);
+            atc,
+            eat,
+            ];
+        brain.sight.iter_mut().for_each(|s|{
+            input.extend([1.0/s.minion as f64, 1.0/s.food as f64, s.food_type as f64, 1.0/s.wall as f64, s.heading as f64]);
+            *s = Sector::default();
+        });
 
-        force.force = Vec2::new(0.0, (out[0] * 20.0).clamp(-20.0, 20.0) as f32);
+        let out = brain.nn.forward(&input);
+
+        let weakness = 1.0 + config.senescence_speed * age.frailty(config.senescence_exponent);
+        let max_force = (20.0 * traits.metabolism / weakness) as f64;
+        force.force = Vec2::new(0.0, (out[0] * max_force).clamp(-max_force, max_force) as f32);
         force.torque = (out[1]/100.0).clamp(-0.01, 0.01) as f32;
+        brain.wants_attack = out[2] > 0.0;
+        brain.wants_eat = out[3] > 0.0;
 
         let dir = Vec2::new( transform.local_x().x, transform.local_x().y);
         force.force = force.force.rotate(dir);
-
-        if transform.translation.x >  7_000.0 {transform.translation.x = -7_000.0}
-        if transform.translation.x < -7_000.0 {transform.translation.x =  7_000.0}
-        if transform.translation.y >  7_000.0 {transform.translation.y = -7_000.0}
-        if transform.translation.y < -7_000.0 {transform.translation.y =  7_000.0}
     });
 }
 
 fn attack(
-    time: Res<Time>,
-    rapier_context: Res<RapierContext>, 
-    mut q_detector: Query<(&Parent, &Weapon, &Collider)>,
-    mut q_minions: Query<(&mut Brain, &mut Health, &mut Hunger), With<Minion>>,
-    mut q_food: Query<&mut Health, (Without<Minion>, With<Food>)>
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    grid: Res<SpatialGrid>,
+    mut q_minions: Query<(Entity, &mut Brain, &mut Health, &mut Hunger, &mut LastAttacker, &Traits, &Transform, &Weapon, &mut Lifetime, &Size), With<Minion>>,
+    mut q_food: Query<(&mut Health, &FoodKind), (Without<Minion>, With<Food>)>
 ){
-    rapier_context.intersection_pairs().filter(|pp| pp.2 ).for_each(|pp|{
-        let mut p = (pp.0, pp.1);
-
-        if q_detector.contains(pp.1) {
-            p = (pp.1, pp.0);
-        }
-
-        if q_detector.contains(p.0) && q_minions.contains(p.1) {
-            let child = q_detector.get_mut(p.0).unwrap();
-            let res = q_minions.get_many_mut([child.0.get(), p.1]);
-
-            if let Ok([mut parent, mut sec]) = res {
-                //parent.0.attacking = true;
-                let dmg = (child.1.damage * time.delta_seconds()) * sec.1.protection;
-                parent.2.filled += dmg/3.0;
-                parent.1.health += dmg/2.0;
-                sec.1.health -= dmg;
+    let weapons: Vec<(Entity, Vec2, Vec2, f32, f32)> = q_minions.iter()
+        .filter(|m| m.1.wants_attack || m.1.wants_eat)
+        .map(|m| (m.0, m.6.translation.truncate(), m.6.local_y().truncate(), m.7.width, m.9.radius + WEAPON_REACH))
+        .collect();
+
+    // what every weapon touches is searched in parallel, but applied in fixed order
+    let hits: Vec<(Entity, Entity)> = weapons.par_iter().flat_map_iter(|&(e, pos, fwd, width, reach)|{
+        let mut out = vec![];
+        let right = Vec2::new(fwd.y, -fwd.x);
+        let r = grid.max_radius();
+        grid.within(pos + fwd * reach, (width + r).hypot(WEAPON_DEPTH + r), |it, d|{
+            if it.entity == e { return; }
+            if d.dot(right).abs() <= width + it.radius && d.dot(fwd).abs() <= WEAPON_DEPTH + it.radius {
+                out.push((e, it.entity));
             }
-        }
+        });
+        out
+    }).collect();
 
-        else if q_detector.contains(p.0) && q_food.contains(p.1) {
-            let child = q_detector.get_mut(p.0).unwrap();
-            let res = q_minions.get_mut(child.0.get());
+    hits.into_iter().for_each(|p|{
+        if q_food.contains(p.1) {
+            let res = q_minions.get_mut(p.0);
             let res_food = q_food.get_mut(p.1);
 
-            if let (Ok(mut parent), Ok(mut food)) = (res, res_food) {
-                //parent.0.eating = true;
-                let dmg = (child.1.damage * time.delta_seconds()) * food.protection;
-                parent.2.filled += dmg * 1.5;
+            if let (Ok(mut parent), Ok((mut food, kind))) = (res, res_food) {
+                if !parent.1.wants_eat { return; }
+                parent.1.eating = true;
+                let dmg = (parent.7.damage * clock.dt) * food.protection;
+                let gain = config.food_types.get(kind.0)
+                    .map_or(0.0, |t| t.nutrition * digestion(parent.5.diet, t, config.digestion_width));
+                parent.3.filled += dmg * config.food_hunger_gain * gain;
+                parent.8.food_eaten += dmg * config.food_hunger_gain * gain;
                 food.health -= dmg;
             }
         }
 
+        else if let Ok([mut parent, mut sec]) = q_minions.get_many_mut([p.0, p.1]) {
+            if !parent.1.wants_attack { return; }
+            parent.1.attacking = true;
+            let dmg = (parent.7.damage * clock.dt) * sec.2.protection;
+            parent.3.filled += dmg * config.attack_hunger_gain;
+            parent.2.health += dmg * config.attack_health_gain;
+            sec.2.health -= dmg;
+            parent.8.damage_dealt += dmg;
+            sec.8.damage_taken += dmg;
+            sec.4.by = Some(p.0);
+            sec.4.at = clock.elapsed;
+        }
     });
 }
 
+// everything within sight radius is looked up in the grid, minions look in parallel
 fn detect( 
-    rapier_context: Res<RapierContext>, 
-    mut q_detector: Query<(&Parent, &Detector, &Collider)>,
-    mut q_minions: Query<(&mut Brain, &Transform, &Sight), With<Minion>>,
-    q_food: Query<&Transform, With<Food>>
+    config: Res<WorldConfig>,
+    grid: Res<SpatialGrid>,
+    mut q_minions: Query<(Entity, &mut Brain, &Transform, &Sight), With<Minion>>,
+    q_walls: Query<(&Wall, &Transform)>,
 ){
-    rapier_context.intersection_pairs().filter(|pp| pp.2 ).for_each(|pp|{
-        let mut p = (pp.0, pp.1);
-
-        if q_detector.contains(pp.1) {
-            p = (pp.1, pp.0);
-        }
-
-        // Other minions
-        if q_detector.contains(p.0) && q_minions.contains(p.1) {
-            let child = q_detector.get_mut(p.0).unwrap();
-
-            if child.0.get() != p.1 {
-                let res = q_minions.get_many_mut([child.0.get(), p.1]);
-
-                if let Ok([mut parent, sec]) = res {
-                    let diff = sec.1.translation - parent.1.translation;
-                    let dst = diff.length()/parent.2.radius/2.0;
-                    let cos_theta = Vec2::new( parent.1.local_x().x, parent.1.local_x().y).normalize().dot((Vec2::new(diff.x, diff.y)).normalize());
-                    let angle: f32 = cos_theta.acos() / PI - 0.5; // angle to minion
-
-                    parent.0.sight_minions.push((angle, dst));
+    let half_angle = config.sight_half_angle.to_radians();
+    let walls: Vec<(&Wall, &Transform)> = q_walls.iter().collect();
+
+    q_minions.par_for_each_mut(16, |(e, mut brain, transform, sight)|{
+        let pos = transform.translation.truncate();
+        let fwd = transform.local_y().truncate();
+
+        grid.within(pos, sight.radius, |it, diff|{
+            if it.entity == e { return; }
+            let dst = diff.length()/sight.radius/2.0;
+            if let Some(b) = in_cone(fwd, diff, half_angle, sight.radius) {
+                if let Some(s) = sector_of(&mut brain.sight, b) {
+                    match it.kind {
+                        ItemKind::Minion => s.see_minion(b, dst),
+                        ItemKind::Food(food_type) => s.see_food(b, dst, food_type),
+                    }
                 }
-            } 
-        }
+            }
+        });
 
-        // Food
-        else if q_detector.contains(p.0) && q_food.contains(p.1) {
-            let child = q_detector.get_mut(p.0).unwrap();
-            let res = q_minions.get_mut(child.0.get());
-            let res_food = q_food.get(p.1);
+        // walls are few and big, so they are checked directly, at their point nearest to minion
+        walls.iter().for_each(|(wall, wt)|{
+            // minion's copy on wall's side of the seam
+            let img = wt.translation.truncate() - grid.delta(pos, wt.translation.truncate());
+            let diff = wall.closest_point(wt, img) - img;
+            let dst = diff.length()/sight.radius/2.0;
+            if let Some(b) = in_cone(fwd, diff, half_angle, sight.radius) {
+                if let Some(s) = sector_of(&mut brain.sight, b) { s.see_wall(b, dst); }
+            }
+        });
+    });
+}
 
-            if let (Ok(mut parent), Ok(food)) = (res, res_food) {
+// spatial index of minions and food for this tick
+fn u_grid(
+    mut grid: ResMut<SpatialGrid>,
+    q_minions: Query<(Entity, &Transform, &Size), With<Minion>>,
+    q_food: Query<(Entity, &Transform, &Size, &FoodKind), With<Food>>,
+    config: Res<WorldConfig>,
+){
+    let minions = q_minions.iter().map(|(entity, t, s)| GridItem{ entity, pos: t.translation.truncate(), radius: s.radius, kind: ItemKind::Minion });
+    let food = q_food.iter().map(|(entity, t, s, k)|{
+        let diet = config.food_types.get(k.0).map_or(0.0, |f| f.diet);
+        GridItem{ entity, pos: t.translation.truncate(), radius: s.radius, kind: ItemKind::Food(diet) }
+    });
+    grid.rebuild(minions.chain(food));
+}
 
-                let diff = food.translation - parent.1.translation;
-                let dst = diff.length()/parent.2.radius/2.0;
-                let cos_theta = Vec2::new( parent.1.local_x().x, parent.1.local_x().y).normalize().dot((Vec2::new(diff.x, diff.y)).normalize());
-                let angle: f32 = cos_theta.acos() / PI - 0.5; // angle to minion
+// newborn appears offspring_distance to the side of parent, but always inside the world,
+// wall in the way brings it closer, onto parent's own spot at worst
+fn offspring_spot(config: &WorldConfig, map: &WorldMap, parent: &Transform, traits: &Traits) -> Vec2 {
+    let radius = Size::default().radius * traits.size;
+    let from = parent.translation.truncate();
+    let dir = parent.local_x().truncate();
+    (0..4)
+        .map(|k| from + dir * config.offspring_distance * (1.0 - k as f32 / 4.0))
+        .map(|p| config.topology.confine(p, config.world_bound, radius))
+        .find(|p| map.is_free(*p, radius))
+        .unwrap_or(from)
+}
 
-                parent.0.sight_food.push((angle, dst));
+// asexual budding, fallback for minions that didn't find a partner,
+// anyone touching minion of own species (so also everyone who just mated) waits for mating instead
+fn reproduce(mut commands: Commands, 
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    map: Res<WorldMap>,
+    rapier_context: Res<RapierContext>,
+    mut rng: ResMut<SimRng>,
+    mut store: ResMut<LineageStore>,
+    mut ev_born: EventWriter<MinionBorn>,
+    mut query: Query<(&Brain, &mut Hunger, &Transform, &Traits, &Lineage, &mut Lifetime, Entity), With<Minion>>,
+    q_species: Query<&Species, With<Minion>>,
+){
+    if !config.budding { return; }
+    let mut partnered: HashSet<Entity> = HashSet::new();
+    if config.mating {
+        rapier_context.contact_pairs().filter(|c| c.has_any_active_contacts()).for_each(|c|{
+            if let (Ok(s1), Ok(s2)) = (q_species.get(c.collider1()), q_species.get(c.collider2())) {
+                if s1 == s2 { partnered.extend([c.collider1(), c.collider2()]); }
             }
+        });
+    }
+    query.iter_mut().for_each(|mut m|{
+        if m.1.filled > config.reproduce_threshold && !partnered.contains(&m.6) {
+            m.1.filled -= config.reproduce_cost;
+            m.5.offspring += 1;
+            let brain = m.0.offspring(config.mutations, &mut rng);
+            let traits = m.3.offspring(config.trait_mutation_rate, config.trait_mutation_size, &mut rng);
+            let pos = offspring_spot(&config, &map, m.2, &traits);
+            let generation = brain.nn.generation as u64;
+            let lineage = store.birth(Some(m.4.id), None, clock.elapsed, generation, &traits);
+            let entity = spawn_minion(&mut commands, MinionState::new(brain, traits, lineage, pos));
+            ev_born.send(MinionBorn { entity, lineage, generation });
         }
-
     });
 }
 
-fn reproduce(mut commands: Commands, 
-    asset_server: Res<AssetServer>, 
-    mut query: Query<(&Brain, &mut Hunger, &Transform), With<Minion>>
+// two touching, well fed minions of same species make one child with crossed over genome
+fn mate(mut commands: Commands, 
+    config: Res<WorldConfig>,
+    map: Res<WorldMap>,
+    mut rng: ResMut<SimRng>,
+    rapier_context: Res<RapierContext>,
+    clock: Res<SimClock>,
+    mut store: ResMut<LineageStore>,
+    mut ev_born: EventWriter<MinionBorn>,
+    mut query: Query<(&Brain, &mut Hunger, &Transform, &Species, &Traits, &Lineage, &mut Lifetime), With<Minion>>
 ){
-    query.iter_mut().for_each(|mut m|{
-        if m.1.filled > 1.5 {
-            m.1.filled -= 0.5;
-            let pos = Vec2::new(m.2.translation.x, m.2.translation.y) + Vec2::new( m.2.local_x().x, m.2.local_x().y) * 500.0;
-            spawn_minion(&mut commands, &asset_server, 
-                &pos,
-                m.0.clone()
-            );
+    if !config.mating { return; }
+    rapier_context.contact_pairs().filter(|c| c.has_any_active_contacts()).for_each(|c|{
+        if let Ok([mut m1, mut m2]) = query.get_many_mut([c.collider1(), c.collider2()]) {
+            if m1.3 != m2.3 { return; }
+            if m1.1.filled < config.mating_threshold || m2.1.filled < config.mating_threshold { return; }
+
+            // fitter parent is the one lineage follows, other is recorded as mate
+            let (child, parent, mate) = if m1.1.filled >= m2.1.filled {
+                (m1.0.crossed(m2.0, &mut rng), m1.5.id, m2.5.id)
+            } else {
+                (m2.0.crossed(m1.0, &mut rng), m2.5.id, m1.5.id)
+            };
+            let traits = m1.4.crossed(m2.4, &mut rng);
+            m1.1.filled -= config.mating_cost;
+            m2.1.filled -= config.mating_cost;
+            m1.6.offspring += 1;
+            m2.6.offspring += 1;
+
+            let brain = child.offspring(config.mutations, &mut rng);
+            let traits = traits.offspring(config.trait_mutation_rate, config.trait_mutation_size, &mut rng);
+            let pos = offspring_spot(&config, &map, m1.2, &traits);
+            let generation = brain.nn.generation as u64;
+            let lineage = store.birth(Some(parent), Some(mate), clock.elapsed, generation, &traits);
+            let entity = spawn_minion(&mut commands, MinionState::new(brain, traits, lineage, pos));
+            ev_born.send(MinionBorn { entity, lineage, generation });
         }
     });
 }
 
 fn u_hunger(
-    time: Res<Time>,
-    mut query: Query<(&mut Hunger, &mut Health, &mut ExternalForce), With<Minion>>
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    mut query: Query<(&mut Hunger, &mut Health, &mut ExternalForce, &Traits, &Age, &Velocity, &mut Lifetime), With<Minion>>
 ) {
-    query.par_for_each_mut(16, |(mut e, mut h, f)|{
-        let energy = (f.force.y.abs() / 40.0) + (f.torque.abs() * 50.0);
+    query.par_for_each_mut(16, |(mut e, mut h, f, t, a, v, mut life)|{
+        let aging = 1.0 + config.senescence_metabolism * a.frailty(config.senescence_exponent);
+        let energy = ((f.force.y.abs() / 40.0) + (f.torque.abs() * 50.0)) * aging;
+        let drain = config.hunger_base_drain + t.upkeep(&config.trait_costs);
+        let spent = clock.dt * e.metabolism * energy + clock.dt * drain;
         if e.filled <= 0.0 {
-            h.health -= time.delta_seconds() * e.metabolism * energy + time.delta_seconds() / 20.0;
+            h.health -= spent;
         } else {
-            e.filled -= time.delta_seconds() * e.metabolism * energy + time.delta_seconds() / 20.0;
+            e.filled -= spent;
         }
-        if e.filled > 2.1 {
-            h.health += e.filled - 2.1;
-            e.filled = 2.1;
+        life.energy_spent += spent;
+        // from velocity, so wrapping across the seam doesn't count as a jump
+        life.distance += v.linvel.length() * clock.dt;
+        if e.filled > config.hunger_cap {
+            h.health += e.filled - config.hunger_cap;
+            e.filled = config.hunger_cap;
         }
         if h.health > 1.0 {
             e.filled += h.health - 1.0;
@@ -279,43 +525,70 @@ fn u_hunger(
     });
 }
 
-fn u_hp(
-    mut commands: Commands,
-    mut query: Query<(&mut Health, Entity)>
-) {
-    query.for_each_mut(|e|{
-        if e.0.health <= 0.0 {commands.entity(e.1).despawn_recursive();}
-    });
+// hits older than that don't make death a predation, unless nothing else explains it
+const PREDATION_WINDOW: f32 = 1.0;
+
+fn death_cause(hunger: &Hunger, age: &Age, attacker: &LastAttacker, now: f32) -> DeathCause {
+    if attacker.by.is_some() && now - attacker.at <= PREDATION_WINDOW { DeathCause::Predation(attacker.by) }
+    else if age.age > age.lifespan { DeathCause::OldAge }
+    else if hunger.filled <= 0.0 { DeathCause::Starvation }
+    // health is lost only to hunger, age and weapons, so it was an old wound
+    else { DeathCause::Predation(attacker.by) }
 }
 
-fn u_age(
-    time: Res<Time>,
-    mut query: Query<(&mut Age, &Brain), With<Minion>>,
+fn u_hp(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    mut ev_died: EventWriter<MinionDied>,
+    query: Query<(&Health, Entity, Option<(&Hunger, &Age, &Brain, &Size, &Transform, &LastAttacker, &Lineage, &Lifetime)>)>
 ) {
-    query.par_for_each_mut(16, |(mut e, _)|{
-        e.age += time.delta_seconds();
+    query.for_each(|e|{
+        if e.0.health > 0.0 { return; }
+        commands.entity(e.1).despawn_recursive();
+
+        // only minions die of causes and leave corpses
+        if let Some((hunger, age, brain, size, transform, attacker, lineage, lifetime)) = e.2 {
+            ev_died.send(MinionDied {
+                entity: e.1,
+                lineage: lineage.id,
+                cause: death_cause(hunger, age, attacker, clock.elapsed),
+                age: age.age,
+                generation: brain.nn.generation as u64,
+                genome: genome_hash(&brain.nn),
+                lifetime: lifetime.clone(),
+            });
+            if config.corpses {
+                let kind = FoodKind(config.corpse_food);
+                let nutrition = config.corpse_nutrition * size.radius / Size::default().radius;
+                let corpse = spawn_food(&mut commands, &transform.translation.truncate(), kind, &config.food_types[kind.0], nutrition);
+                commands.entity(corpse).insert(Corpse::default());
+            }
+        }
     });
 }
 
-fn u_sight(
-    q_minions: Query<(&Children, &Sight), (With<Minion>, Changed<Sight>)>,
-    mut q_detector: Query<&mut Collider, With<Detector>>,
+// killer gets credit once its victim is gone
+fn u_kills(
+    mut events: EventReader<MinionDied>,
+    mut query: Query<&mut Lifetime, With<Minion>>,
 ){
-    q_minions.iter().for_each(|c|{
-        c.0.iter().for_each(|&id|{
-            if q_detector.contains(id) {
-                let mut child = q_detector.get_mut(id).unwrap();
-                child.set_scale(Vec2::new(c.1.radius, c.1.radius), 1);
-            }
-        });
+    events.iter().for_each(|ev|{
+        if let DeathCause::Predation(Some(killer)) = ev.cause {
+            if let Ok(mut l) = query.get_mut(killer) { l.kills += 1; }
+        }
     });
 }
 
-fn u_weapon(
-    mut q_weapons: Query<(&Weapon, &mut Collider), Changed<Weapon>>,
-){
-    q_weapons.par_for_each_mut(16, |mut w|{
-        w.1.set_scale(Vec2::new(w.0.width, 4.0), 1);
+// past lifespan minions slowly fall apart
+fn u_age(
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    mut query: Query<(&mut Age, &mut Health), With<Minion>>,
+) {
+    query.par_for_each_mut(16, |(mut e, mut h)|{
+        e.age += clock.dt;
+        h.health -= clock.dt * config.senescence_health * e.frailty(config.senescence_exponent);
     });
 }
 
@@ -328,44 +601,33 @@ fn u_minion(
     });
 }
 
-fn u_food(time: Res<Time>,
-    mut commands: Commands, 
-    asset_server: Res<AssetServer>,
-    query: Query<&Food>
-) {
-    let target_amount: i64;
-    if time.elapsed_seconds() < 180.0 { target_amount = 512; }
-    else if time.elapsed_seconds() < 360.0 { target_amount = 192; }
-    else { target_amount = 64; }
-
-    let amount_missing = target_amount - query.iter().len() as i64;
-
-    if amount_missing > 0 { 
-        //let norm = Normal::new(0.0, 0.8).unwrap();
-        //let x = thread_rng().sample::<f32, _>(norm) - 0.5;
-        //let y = thread_rng().sample::<f32, _>(norm) - 0.5;
-        let position = Vec2::new((fr::f32()-0.5)*4000.0, (fr::f32() - 0.5)*4000.0);
-        spawn_food(&mut commands, &asset_server, &position);
-    }
+// every frame is one tick of fixed length, same as physics step
+fn u_clock(config: Res<WorldConfig>, mut clock: ResMut<SimClock>) {
+    clock.dt = config.timestep;
+    clock.elapsed += clock.dt;
+    clock.tick += 1;
 }
 
+// S saves oldest brain and whole world, L restores world
 fn save_best(
+    config: Res<WorldConfig>,
     mut best: ResMut<BestNN>,
-    mut query: Query<(&Age, &mut Brain)>,
+    query: Query<(&Age, &Brain)>,
     keys: Res<Input<KeyCode>>,
+    mut ev_save: EventWriter<SaveSnapshot>,
+    mut ev_load: EventWriter<LoadSnapshot>,
 ){
     if keys.just_pressed(KeyCode::S) {
-        let bb = query.iter().max_by(|a, b| a.0.age.partial_cmp(&b.0.age).unwrap()).unwrap();
-        best.age = bb.0.age;
-        best.nn = bb.1.nn.clone();
-        best.nn.save("nn.dat");
-        println!("Saved: {}", best.age);
+        if let Some(bb) = query.iter().max_by(|a, b| a.0.age.partial_cmp(&b.0.age).unwrap()) {
+            best.age = bb.0.age;
+            best.nn = bb.1.nn.clone();
+            best.nn.save(&config.output("nn.dat"));
+            println!("Saved: {}", best.age);
+        }
+        ev_save.send(SaveSnapshot(config.output(SNAPSHOT_PATH)));
     }
     if keys.just_pressed(KeyCode::L) {
-        query.par_for_each_mut(2, |mut e|{
-            e.1.nn.load("nn.dat");
-        });
-        println!("Loaded");
+        ev_load.send(LoadSnapshot(config.output(SNAPSHOT_PATH)));
     }
 }
 
@@ -376,20 +638,59 @@ pub struct MainPlugin;
 impl Plugin for MainPlugin {
     fn build(&self, app: &mut App){
         app
+            .add_plugin(SpeedPlugin)
             .add_startup_system(init_minions)
-            //.add_startup_system(spawn_env)
-            .add_system(u_food)
-            .add_system(u_sight)
-            .add_system(u_weapon)
-            .add_system(u_minion)
-            .add_system(u_age)
-            .add_system(u_hunger)
-            .add_system(u_hp)
-            .add_system(ai)
-            .add_system(detect)
-            .add_system(attack)
-            .add_system(reproduce)
+            // fixed order, so same seed always gives same world
+            .add_sim_system(u_clock)
+            .add_sim_system(u_minion.after(u_clock))
+            .add_sim_system(wrap_bodies.after(u_minion))
+            .add_sim_system(u_grid.after(wrap_bodies))
+            .add_sim_system(detect.after(u_grid))
+            .add_sim_system(ai.after(detect))
+            .add_sim_system(attack.after(ai))
+            .add_sim_system(u_hunger.after(attack))
+            .add_sim_system(u_age.after(u_hunger))
+            .add_sim_system(mate.after(u_age))
+            .add_sim_system(reproduce.after(mate))
+            .add_sim_system(u_hp.after(reproduce))
+            .add_sim_system(u_kills.after(u_hp))
+            .add_sim_system(u_plants.after(u_hp))
+            .add_sim_system(u_corpses.after(u_hp))
+            .add_sim_system(spread_plants.after(u_plants))
+            .insert_resource(MinionAmount(0))
+            .init_resource::<WorldConfig>()
+            .init_resource::<SimClock>()
+            .init_resource::<SimRng>()
+            .add_plugin(MapPlugin)
+            .add_plugin(PlantPlugin)
+            .add_plugin(SnapshotPlugin)
+            .add_plugin(SpeciesPlugin)
+            .add_plugin(StatsPlugin)
+            .add_plugin(LineagePlugin)
+            .add_plugin(HallPlugin)
+            .add_plugin(ExtinctionPlugin)
+            .add_event::<MinionDied>()
+            .add_event::<MinionBorn>()
+        ;
+        let grid = {
+            let config = app.world.resource::<WorldConfig>();
+            let torus = (config.topology == Topology::Torus).then_some(2.0 * config.world_bound);
+            SpatialGrid::new(config.grid_cell, torus)
+        };
+        app.insert_resource(grid);
+    }
+}
+
+// everything that needs a window, keyboard or textures, 
+// kept out of MainPlugin so it can run headless
+pub struct InteractPlugin;
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .add_startup_system(init_cursor)
+            .add_system(u_sprites)
             .add_system(movement)
+            .add_system(speed_keys)
             .add_system(save_best)
             .add_system(u_cursor)
             .insert_resource(BestNN::default())
@@ -397,29 +698,109 @@ impl Plugin for MainPlugin {
     }
 }
 
+fn rapier_config(config: &WorldConfig) -> RapierConfiguration {
+    RapierConfiguration{
+        gravity: Vec2::new(0.0, 0.0), 
+        timestep_mode: TimestepMode::Fixed{dt: config.timestep, substeps: 1}, 
+        ..default()
+    }
+}
+
+// whole simulation without window, `config.seed` has to be already decided
+fn headless_app(config: WorldConfig, limit: RunLimit, snapshot: Option<String>) -> App {
+    let mut app = App::new();
+    app
+        .add_plugin(HeadlessPlugin{limit})
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).with_default_system_setup(false))
+        .insert_resource(rapier_config(&config))
+        .insert_resource(SimRng::new(config.seed.unwrap_or_default()))
+        .insert_resource(config)
+        .insert_resource(SnapshotOnStart(snapshot))
+        .add_plugin(MainPlugin)
+    ;
+    app
+}
+
+// bad arguments end with message on stderr instead of panic, 2 for command line, 1 for anything else
+fn exit_with(message: String, code: i32) -> ! {
+    eprintln!("{message}");
+    std::process::exit(code)
+}
+
 const BACKGROUND_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let cli = Cli::parse(&args).unwrap_or_else(|e| exit_with(format!("{e}\n\n{USAGE}"), 2));
+    match &cli.command {
+        Command::Help => { println!("{USAGE}"); return; }
+        Command::InspectNn(path) => { inspect_nn(path).unwrap_or_else(|e| exit_with(format!("Invalid brain: {e}"), 1)); return; }
+        Command::InspectHall(path) => { inspect_hall(path).unwrap_or_else(|e| exit_with(format!("Invalid hall of fame: {e}"), 1)); return; }
+        Command::Batch(path) => { run_batch(&cli, path).unwrap_or_else(|e| exit_with(format!("Batch failed: {e}"), 1)); return; }
+        _ => {}
+    }
+    let mut config = cli.world_config().unwrap_or_else(|e| exit_with(format!("Invalid config: {e}"), 1));
+    let seed = config.seed.unwrap_or_else(|| fr::u64(..));
+    config.seed = Some(seed);
+    println!("Seed: {seed}");
+    prepare_out_dir(&config).unwrap_or_else(|e| exit_with(format!("Invalid out dir: {e}"), 1));
+
+    // no window, no renderer, just simulation for given amount of time
+    if let Command::Headless(limit) = cli.command {
+        headless_app(config, limit, cli.snapshot()).run();
+        return;
+    }
+
     App::new()
         //.insert_resource(WindowDescriptor{scale_factor_override: Some(1.0),..default()})
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).with_default_system_setup(false))
         //.add_plugin(RapierDebugRenderPlugin::default())
-        .insert_resource(RapierConfiguration{gravity: Vec2::new(0.0, 0.0), ..default()})
+        .insert_resource(rapier_config(&config))
+        .insert_resource(SimRng::new(seed))
         .insert_resource(ClearColor(BACKGROUND_COLOR))
+        .insert_resource(config)
+        .insert_resource(SnapshotOnStart(cli.snapshot()))
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(MainPlugin)
+        .add_plugin(InteractPlugin)
         .add_plugin(UiManPlugin)
         .add_system(bevy::window::close_on_esc)
         .run();
 }
 
+// simulation spawns bare entities, textures are attached here only when rendering
+fn u_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<WorldConfig>,
+    q_minions: Query<(Entity, &Size), Added<Minion>>,
+    q_food: Query<(Entity, &FoodKind), Added<Food>>,
+    q_walls: Query<(Entity, &Wall), Added<Wall>>,
+){
+    q_minions.iter().for_each(|(e, size)|{
+        let texture: Handle<Image> = asset_server.load("../assets/textures/Minion.png");
+        let sprite = Sprite { custom_size: Some(Vec2::splat(size.radius * 2.0)), ..default() };
+        commands.entity(e).insert((sprite, texture));
+    });
+    q_food.iter().for_each(|(e, kind)|{
+        let food = &config.food_types[kind.0];
+        let texture: Handle<Image> = asset_server.load(format!("../assets/textures/{}", food.texture));
+        let [r, g, b] = food.colour;
+        commands.entity(e).insert((Sprite { color: Color::rgb(r, g, b), ..default() }, texture));
+    });
+    q_walls.iter().for_each(|(e, wall)|{
+        let sprite = Sprite { color: Color::NAVY, custom_size: Some(wall.half_size * 2.0), ..default() };
+        commands.entity(e).insert((sprite, DEFAULT_IMAGE_HANDLE.typed::<Image>()));
+    });
+}
+
 fn u_cursor(
-    rapier_context: Res<RapierContext>,
+    grid: Res<SpatialGrid>,
     buttons: Res<Input<MouseButton>>,
     c_pos: Res<CursorWorld>,
     mut sel: ResMut<SelectedNN>,
     mut q_cursor: Query<&mut Transform, (Without<Minion>, With<Cursor>)>,
-    q_minions: Query<(Entity, &Brain, &Transform, &Health, &Hunger, &Age), With<Minion>>
+    q_minions: Query<(Entity, &Brain, &Transform, &Health, &Hunger, &Age, Option<&Species>, &Lineage, &Lifetime), With<Minion>>
 ){
     if let Ok(mut cc) = q_cursor.get_single_mut() {
         let z = cc.translation.z;
@@ -431,22 +812,20 @@ fn u_cursor(
             sel.hp = minion.3.health;
             sel.hunger = minion.4.filled;
             sel.age = minion.5.age;
+            sel.species = minion.6.map(|s| s.id);
+            sel.lineage = minion.7.id;
+            sel.lifetime = minion.8.clone();
         }
     }
     
+    // minion under the cursor, nearest one when they overlap
     if buttons.just_pressed(MouseButton::Left) {
-        rapier_context.intersection_pairs().filter(|pp| pp.2 ).for_each(|pp|{
-            let mut p = (pp.0, pp.1);
-
-            if q_cursor.contains(pp.1) {
-                p = (pp.1, pp.0);
-            }
-
-            if q_cursor.contains(p.0) && q_minions.contains(p.1) {
-                let minion = q_minions.get(p.1).unwrap();
-                sel.eid = minion.0;
+        let mut best = f32::MAX;
+        grid.within(Vec2::new(c_pos.x, c_pos.y), grid.max_radius(), |it, d|{
+            if it.kind == ItemKind::Minion && d.length() <= it.radius && d.length() < best {
+                best = d.length();
+                sel.eid = it.entity;
             }
-
         });
     }
 }
@@ -462,11 +841,17 @@ fn u_cursor(
 
 
 
-fn spawn_food(commands: &mut Commands, asset_server: &Res<AssetServer>, position: &Vec2) {
-    let member = Group::from_bits(0b10010000000000000000000000000000).unwrap();
+fn spawn_food(commands: &mut Commands, position: &Vec2, kind: FoodKind, food: &FoodType, health: f32) -> Entity {
+    // plants are food(A), meat is food(B)
+    let member = if food.meat { 
+        Group::from_bits(0b10001000000000000000000000000000).unwrap() 
+    } else { 
+        Group::from_bits(0b10010000000000000000000000000000).unwrap() 
+    };
     let filter = Group::from_bits(0b11111000000000000000000000000000).unwrap();
     commands.spawn(Food) 
-        .insert(Health::new(0.99))
+        .insert(kind)
+        .insert(Health{health, protection: food.protection})
         .insert(Size{radius: 8.0})
         .insert(RigidBody::Dynamic)
         .insert(Damping {linear_damping: 0.98, angular_damping: 0.98 })
@@ -474,31 +859,64 @@ fn spawn_food(commands: &mut Commands, asset_server: &Res<AssetServer>, position
         .insert(CollisionGroups::new(member, filter))
         .insert(Restitution::coefficient(0.0))
         .insert(Friction::coefficient(0.5))
-        .insert(SpriteBundle {
+        .insert(SpatialBundle {
             transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.0)),
-            texture: asset_server.load("../assets/textures/Food_a.png"),
             ..default()
         })
-        ;
+        .id()
 }
 
 
-fn init_minions(mut commands: Commands, asset_server: Res<AssetServer>) {
-    for i in 0..420 {
+fn init_minions(mut commands: Commands, 
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>, 
+    map: Res<WorldMap>,
+    mut rng: ResMut<SimRng>,
+    mut store: ResMut<LineageStore>,
+    mut ev_born: EventWriter<MinionBorn>,
+    hall: Res<HallOfFame>,
+) {
+    let from_hall: Vec<&HallEntry> = config.initial_hall.iter().map(|r|{
+        hall.entries.get(*r).unwrap_or_else(|| panic!("Hall of fame has only {} entries, no {r}", hall.entries.len()))
+    }).collect();
+    // every founder gets its own random brain, unless one was given to start from
+    let start = config.initial_brain.as_ref().map(|path|{
+        let mut nn = NN::new(0, 0);
+        nn.load(path);
+        println!("Starting brain: {path}");
+        Brain::with_nn(nn, config.sight_sectors)
+    });
+    for i in 0..config.initial_minions as i32 {
         //let norm = Normal::new(0.0, 0.8).unwrap();
         //let x = thread_rng().sample::<f32, _>(norm) - 0.5;
         //let y = thread_rng().sample::<f32, _>(norm) - 0.5;
-        let position = Vec2::new((fr::f32() - 0.5)*4000.0, (fr::f32() - 0.5)*4000.0);
+        let position = map.spawn_point(&config, &mut rng);
 
-        let p = spawn_minion(&mut commands, &asset_server, &position, Brain::default());
-        if i == -1 {commands.entity(p).insert(Control);}
+        let (brain, traits) = if !from_hall.is_empty() {
+            from_hall[i as usize % from_hall.len()].founder(&config, &mut rng)
+        } else {
+            let brain = match &start {
+                Some(b) => b.offspring(config.mutations, &mut rng),
+                None => Brain::new(config.sight_sectors, &mut rng).offspring(config.mutations, &mut rng),
+            };
+            (brain, Traits::default().offspring(config.trait_mutation_rate, config.trait_mutation_size, &mut rng))
+        };
+        let born = spawn_founder(&mut commands, &mut store, clock.elapsed, brain, traits, position);
+        if i == -1 {commands.entity(born.entity).insert(Control);}
+        ev_born.send(born);
     }
-    let member = Group::from_bits(0b00100000000000000000000000000000).unwrap();
-    let filter = Group::from_bits(0b00100000000000000000000000000000).unwrap();
+}
+
+// minion without parents, at start and whenever population is refilled
+fn spawn_founder(commands: &mut Commands, store: &mut LineageStore, born: f32, brain: Brain, traits: Traits, position: Vec2) -> MinionBorn {
+    let generation = brain.nn.generation as u64;
+    let lineage = store.birth(None, None, born, generation, &traits);
+    let entity = spawn_minion(commands, MinionState::new(brain, traits, lineage, position));
+    MinionBorn { entity, lineage, generation }
+}
+
+fn init_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Cursor)
-        .insert(Collider::ball(1.0))
-        .insert(Sensor)
-        .insert(CollisionGroups::new(member, filter))
         .insert(SpriteBundle {
             transform: Transform::default(),
             texture: asset_server.load("../assets/textures/Cursor.png"),
@@ -507,52 +925,36 @@ fn init_minions(mut commands: Commands, asset_server: Res<AssetServer>) {
 }
 
 // It is NOT a system, but to be used by systems to spawn minions
-fn spawn_minion(commands: &mut Commands, asset_server: &Res<AssetServer>, position: &Vec2, brain: Brain) -> Entity {
-    let mut brain = brain;
-    brain.nn.mutate();
-    brain.nn.mutate();
-    brain.nn.mutate();
+fn spawn_minion(commands: &mut Commands, state: MinionState) -> Entity {
     let member = Group::from_bits(0b10100000000000000000000000000000).unwrap();
     let filter = Group::from_bits(0b11111000000000000000000000000000).unwrap();
     let p = commands.spawn(Minion) 
-        .insert(brain)
-        .insert(Health::default())
-        .insert(Age::default())
-        .insert(Hunger::default())
-        .insert(Sight::default())
-        .insert(Size::default())
+        .insert(state.brain)
+        .insert(state.health)
+        .insert(state.age)
+        .insert(state.hunger)
+        .insert(state.sight)
+        .insert(state.size)
+        .insert(state.traits)
+        .insert(state.lineage)
+        .insert(state.weapon)
+        .insert(state.lifetime)
+        .insert(LastAttacker::default())
         .insert(RigidBody::Dynamic)
-        .insert(Velocity::default())
+        .insert(Velocity{linvel: Vec2::from(state.linvel), angvel: state.angvel})
         .insert(ExternalForce::default())
         .insert(Damping {linear_damping: 0.98, angular_damping: 0.98 })
         .insert(Collider::ball(16.0))
         .insert(CollisionGroups::new(member, filter))
         .insert(Restitution::coefficient(0.0))
         .insert(Friction::coefficient(0.5))
-        .insert(SpriteBundle {
-            transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.0)),
-            texture: asset_server.load("../assets/textures/Minion.png"),
+        .insert(SpatialBundle {
+            transform: Transform::from_translation(Vec3::new(state.position[0], state.position[1], 0.0))
+                .with_rotation(Quat::from_rotation_z(state.rotation)),
             ..default()
         })
         .id();
-        
-    let joint_d = FixedJointBuilder::new().local_anchor1(Vec2::new(0.0, 30.0));
-    commands.spawn(Detector)
-        .insert(RigidBody::Dynamic)
-        .insert(Collider::ball(1.0))
-        .insert(ColliderMassProperties::Mass(0.000001))
-        .insert(Sensor)
-        .set_parent(p)
-        .insert(ImpulseJoint::new(p, joint_d));
-
-    let joint_w = FixedJointBuilder::new().local_anchor1(Vec2::new(0.0, 24.0));
-    commands.spawn(Weapon::default())
-        .insert(RigidBody::Dynamic)
-        .insert(Collider::cuboid(1.0, 1.0))
-        .insert(ColliderMassProperties::Mass(0.000001))
-        .insert(Sensor)
-        .set_parent(p)
-        .insert(ImpulseJoint::new(p, joint_w));
+    if let Some(sp) = state.species { commands.entity(p).insert(sp); }
     p
 }
 
@@ -574,26 +976,6 @@ fn movement(
     });
 }
 
-//fn spawn_env(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<ColorMaterial>>) {
-//    let member = Group::from_bits(0b11000000000000000000000000000000).unwrap();
-//    let filter = Group::from_bits(0b11111000000000000000000000000000).unwrap();
-//    commands
-//        .spawn(Collider::cuboid(500.0, 50.0))
-//        .insert(CollisionGroups::new(member, filter))
-//        .insert(MaterialMesh2dBundle {
-//            mesh: meshes.add(shape::Quad::new(Vec2::new(500.0, 50.0)*2.0).into()).into(),
-//            material: materials.add(ColorMaterial::from(Color::NAVY)),
-//            transform: Transform::from_translation(Vec3::new(800., 0., 0.)),
-//            ..default()});
-//    commands
-//        .spawn(Collider::cuboid(100.0, 100.0))
-//        .insert(CollisionGroups::new(member, filter))
-//        .insert(MaterialMesh2dBundle {
-//            mesh: meshes.add(shape::Quad::new(Vec2::new(100.0, 100.0)*2.0).into()).into(),
-//            material: materials.add(ColorMaterial::from(Color::NAVY)),
-//            transform: Transform::from_translation(Vec3::new(900., 600., 0.)),
-//            ..default()});
-//}
 
 
 
@@ -644,3 +1026,47 @@ fn movement(
 //
 //    });
 //}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::AppExit;
+
+    // population after every tick, and where everyone ended up
+    #[derive(Debug, Default, Clone, PartialEq, Resource)]
+    struct Trace {
+        population: Vec<usize>,
+        end: Vec<(u64, [f32; 2])>,
+    }
+
+    fn u_trace(mut trace: ResMut<Trace>, q_minions: Query<(), With<Minion>>) {
+        trace.population.push(q_minions.iter().len());
+    }
+
+    fn run(seed: u64, ticks: u64) -> Trace {
+        let config = WorldConfig { seed: Some(seed), initial_minions: 40, initial_plants: 128, ..default() };
+        let mut app = headless_app(config, RunLimit::Ticks(ticks), None);
+        app
+            .init_resource::<Trace>()
+            .add_sim_system(u_trace.after(u_hp))
+        ;
+        while app.world.resource::<Events<AppExit>>().is_empty() { app.update(); }
+
+        let mut end: Vec<(u64, [f32; 2])> = app.world
+            .query_filtered::<(&Lineage, &Transform), With<Minion>>()
+            .iter(&app.world)
+            .map(|(l, t)| (l.id.0, t.translation.truncate().to_array()))
+            .collect();
+        end.sort_by_key(|e| e.0);
+        Trace { end, ..app.world.resource::<Trace>().clone() }
+    }
+
+    #[test]
+    fn same_seed_gives_same_run() {
+        let a = run(7, 300);
+        let b = run(7, 300);
+        assert_eq!(a.population.len(), 300);
+        assert_eq!(a, b);
+        assert_ne!(a, run(8, 300));
+    }
+}
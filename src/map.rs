@@ -0,0 +1,168 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{Size, SimRng, WorldConfig};
+use crate::topology::Topology;
+
+// axis aligned rectangle in world coordinates
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Area {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+impl Area {
+    pub fn contains(&self, p: Vec2) -> bool {
+        p.x >= self.min[0] && p.x <= self.max[0] && p.y >= self.min[1] && p.y <= self.max[1]
+    }
+    fn size(&self) -> Vec2 {
+        (Vec2::from(self.max) - Vec2::from(self.min)).max(Vec2::ZERO)
+    }
+    pub fn random_point(&self, rng: &mut SimRng) -> Vec2 {
+        Vec2::from(self.min) + self.size() * Vec2::new(rng.f32(), rng.f32())
+    }
+
+    // one of `areas` picked proportionally to its size, none when they are all empty
+    pub fn pick<'a>(areas: &'a [Area], rng: &mut SimRng) -> Option<&'a Area> {
+        let total: f32 = areas.iter().map(|a| a.size().x * a.size().y).sum();
+        if total <= 0.0 { return None; }
+        let mut left = rng.f32() * total;
+        areas.iter()
+            .find(|a| { left -= a.size().x * a.size().y; left < 0.0 })
+            .or(areas.last())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WallDef {
+    pub center: [f32; 2],
+    pub half_size: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32, // degrees
+}
+impl WallDef {
+    pub fn contains(&self, p: Vec2) -> bool {
+        self.overlaps(p, 0.0)
+    }
+    // whether body of `radius` at `p` would touch it, corners are treated as square
+    pub fn overlaps(&self, p: Vec2, radius: f32) -> bool {
+        let local = Vec2::from_angle(-self.rotation.to_radians()).rotate(p - Vec2::from(self.center));
+        local.x.abs() <= self.half_size[0] + radius && local.y.abs() <= self.half_size[1] + radius
+    }
+}
+
+// soil inside overrides random fertility
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FertileZone {
+    pub area: Area,
+    pub fertility: f32,
+}
+
+const SPAWN_TRIES: usize = 32;
+
+// static layout of the world, read from `map_path` (RON, or JSON for .json files)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Resource)]
+#[serde(default)]
+pub struct WorldMap {
+    pub walls: Vec<WallDef>,
+    pub fertile: Vec<FertileZone>, // later zones win where they overlap
+    pub spawn: Vec<Area>,          // first minions appear only here, whole spawn_area when empty
+}
+impl WorldMap {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        if path.ends_with(".json") {
+            serde_json::from_str(&text).map_err(|e| format!("{path}: {e}"))
+        } else {
+            ron::from_str(&text).map_err(|e| format!("{path}: {e}"))
+        }
+    }
+
+    // where body of `radius` can be put without sticking into a wall
+    pub fn is_free(&self, p: Vec2, radius: f32) -> bool {
+        !self.walls.iter().any(|w| w.overlaps(p, radius))
+    }
+
+    // random spawn point clear of walls, tried again few times when it hits one,
+    // so badly drawn spawn areas still end up somewhere
+    pub fn spawn_point(&self, config: &WorldConfig, rng: &mut SimRng) -> Vec2 {
+        let mut p = self.random_spawn(config, rng);
+        for _ in 0..SPAWN_TRIES {
+            if self.is_free(p, Size::default().radius) { break; }
+            p = self.random_spawn(config, rng);
+        }
+        p
+    }
+
+    // spawn areas are picked proportionally to their size
+    fn random_spawn(&self, config: &WorldConfig, rng: &mut SimRng) -> Vec2 {
+        match Area::pick(&self.spawn, rng) {
+            Some(area) => area.random_point(rng),
+            None => Vec2::new((rng.f32() - 0.5) * config.spawn_area, (rng.f32() - 0.5) * config.spawn_area),
+        }
+    }
+}
+impl FromWorld for WorldMap {
+    fn from_world(world: &mut World) -> Self {
+        match &world.resource::<WorldConfig>().map_path {
+            // checked by validate already
+            Some(path) => WorldMap::load(path).unwrap_or_else(|e| panic!("Invalid map: {e}")),
+            None => WorldMap::default(),
+        }
+    }
+}
+
+// static obstacle in environment collision group, minions bump into it and detectors see it
+#[derive(Debug, Clone, Component)]
+pub struct Wall {
+    pub half_size: Vec2,
+}
+impl Wall {
+    // point of wall nearest to `p`, used as what minions see of it
+    pub fn closest_point(&self, transform: &Transform, p: Vec2) -> Vec2 {
+        let center = transform.translation.truncate();
+        let rot = transform.rotation.to_euler(EulerRot::XYZ).2;
+        let local = Vec2::from_angle(-rot).rotate(p - center).clamp(-self.half_size, self.half_size);
+        center + Vec2::from_angle(rot).rotate(local)
+    }
+}
+
+// four walls just outside of world bound
+fn border_walls(bound: f32) -> Vec<WallDef> {
+    let t = 50.0;
+    vec![
+        WallDef { center: [0.0, bound + t], half_size: [bound + 2.0 * t, t], rotation: 0.0 },
+        WallDef { center: [0.0, -bound - t], half_size: [bound + 2.0 * t, t], rotation: 0.0 },
+        WallDef { center: [bound + t, 0.0], half_size: [t, bound], rotation: 0.0 },
+        WallDef { center: [-bound - t, 0.0], half_size: [t, bound], rotation: 0.0 },
+    ]
+}
+
+fn spawn_walls(mut commands: Commands, config: Res<WorldConfig>, map: Res<WorldMap>) {
+    let member = Group::from_bits(0b11000000000000000000000000000000).unwrap();
+    let filter = Group::from_bits(0b11111000000000000000000000000000).unwrap();
+    let border = if config.topology == Topology::Bounded { border_walls(config.world_bound) } else { vec![] };
+    map.walls.iter().chain(border.iter()).for_each(|w|{
+        commands.spawn(Wall{half_size: Vec2::from(w.half_size)})
+            .insert(RigidBody::Fixed)
+            .insert(Collider::cuboid(w.half_size[0], w.half_size[1]))
+            .insert(CollisionGroups::new(member, filter))
+            .insert(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(w.center[0], w.center[1], 0.0))
+                    .with_rotation(Quat::from_rotation_z(w.rotation.to_radians())),
+                ..default()
+            });
+    });
+}
+
+pub struct MapPlugin;
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<WorldMap>()
+            .add_startup_system(spawn_walls)
+        ;
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{Health, SimClock, SimRng, WorldConfig, spawn_food};
+use crate::food::{FoodKind, pick_plant_kind};
+use crate::map::{Area, WorldMap, WallDef, FertileZone};
+
+// food that grows
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Component)]
+pub struct Plant {
+    pub age: f32,
+}
+
+// food left by dead minion, doesn't grow and rots away after corpse_lifespan
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Component)]
+pub struct Corpse {
+    pub age: f32,
+}
+
+// how good soil is in every place of the world, smooth random hills between fertility_min and 1.0,
+// zones from map override it and nothing grows inside walls,
+// only noise seed is kept so it is cheap to snapshot
+#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+pub struct Fertility {
+    seed: u64,
+    cell: f32,
+    min: f32,
+    zones: Vec<FertileZone>,
+    walls: Vec<WallDef>,
+}
+impl FromWorld for Fertility {
+    fn from_world(world: &mut World) -> Self {
+        let (cell, min) = {
+            let config = world.resource::<WorldConfig>();
+            (config.fertility_cell, config.fertility_min)
+        };
+        let (zones, walls) = {
+            let map = world.resource::<WorldMap>();
+            (map.fertile.clone(), map.walls.clone())
+        };
+        Self { seed: world.resource_mut::<SimRng>().u64(), cell, min, zones, walls }
+    }
+}
+impl Fertility {
+    // value noise, random value in every grid corner blended smoothly in between
+    pub fn at(&self, pos: Vec2) -> f32 {
+        if self.walls.iter().any(|w| w.contains(pos)) { return 0.0; }
+        if let Some(z) = self.zones.iter().rev().find(|z| z.area.contains(pos)) { return z.fertility; }
+
+        let p = pos / self.cell;
+        let (x0, y0) = (p.x.floor(), p.y.floor());
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (tx, ty) = (smooth(p.x - x0), smooth(p.y - y0));
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.corner(x0, y0) * (1.0 - tx) + self.corner(x0 + 1, y0) * tx;
+        let bottom = self.corner(x0, y0 + 1) * (1.0 - tx) + self.corner(x0 + 1, y0 + 1) * tx;
+        let v = top * (1.0 - ty) + bottom * ty;
+        self.min + (1.0 - self.min) * v
+    }
+
+    fn corner(&self, x: i64, y: i64) -> f32 {
+        let mut h = self.seed ^ (x as u64).wrapping_mul(0x9E3779B97F4A7C15) ^ (y as u64).wrapping_mul(0xC2B2AE3D27D4EB4F);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xFF51AFD7ED558CCD);
+        h ^= h >> 33;
+        (h >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn cell_of(&self, pos: Vec2) -> (i64, i64) {
+        ((pos.x / self.cell).floor() as i64, (pos.y / self.cell).floor() as i64)
+    }
+}
+
+pub fn spawn_plant(commands: &mut Commands, config: &WorldConfig, position: &Vec2, kind: FoodKind, plant: Plant, nutrition: f32) -> Entity {
+    let id = spawn_food(commands, position, kind, &config.food_types[kind.0], nutrition);
+    commands.entity(id).insert(plant);
+    id
+}
+
+// random point where plant of `kind` grows (its areas, or food area), more fertile places are picked more often
+fn fertile_spot(config: &WorldConfig, fertility: &Fertility, kind: FoodKind, rng: &mut SimRng) -> Option<Vec2> {
+    let pos = match Area::pick(&config.food_types[kind.0].areas, rng) {
+        Some(area) => area.random_point(rng),
+        None => Vec2::new((rng.f32() - 0.5) * config.food_area, (rng.f32() - 0.5) * config.food_area),
+    };
+    (rng.f32() < fertility.at(pos)).then_some(pos)
+}
+
+fn init_plants(mut commands: Commands, config: Res<WorldConfig>, fertility: Res<Fertility>, mut rng: ResMut<SimRng>) {
+    let mut planted = 0;
+    // rejection sampling, bounded in case whole world is barren
+    for _ in 0..config.initial_plants * 16 {
+        if planted >= config.initial_plants { break; }
+        let kind = pick_plant_kind(&config.food_types, rng.f32());
+        if let Some(pos) = fertile_spot(&config, &fertility, kind, &mut rng) {
+            spawn_plant(&mut commands, &config, &pos, kind, Plant::default(), config.plant_max_nutrition);
+            planted += 1;
+        }
+    }
+}
+
+// plants ripen with age, faster on good soil, and wither once they get too old
+pub fn u_plants(
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    fertility: Res<Fertility>,
+    mut query: Query<(&mut Plant, &mut Health, &Transform, &FoodKind)>,
+){
+    query.par_for_each_mut(16, |(mut p, mut h, t, k)|{
+        p.age += clock.dt;
+        if p.age > config.plant_lifespan {
+            h.health = 0.0;
+            return;
+        }
+        let growth = config.plant_growth * config.food_types[k.0].growth * fertility.at(t.translation.truncate());
+        h.health = (h.health + growth * clock.dt).min(config.plant_max_nutrition);
+    });
+}
+
+// uneaten corpses don't stay around forever
+pub fn u_corpses(
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    mut query: Query<(&mut Corpse, &mut Health)>,
+){
+    query.par_for_each_mut(16, |(mut c, mut h)|{
+        c.age += clock.dt;
+        if c.age > config.corpse_lifespan { h.health = 0.0; }
+    });
+}
+
+// ripe plants drop seeds around (only where their kind grows), and few sprout anywhere so barren world can recover,
+// seed only takes root when its patch of soil isn't already full of plants (corpses don't take room)
+pub fn spread_plants(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    fertility: Res<Fertility>,
+    mut rng: ResMut<SimRng>,
+    q_plants: Query<(&Health, &Transform, &FoodKind), With<Plant>>,
+){
+    let mut crowd: HashMap<(i64, i64), usize> = HashMap::new();
+    q_plants.iter().for_each(|(_, t, _)| *crowd.entry(fertility.cell_of(t.translation.truncate())).or_default() += 1);
+    let mut total = q_plants.iter().len();
+
+    // seeds keep kind of their plant, sprouts get random one
+    let mut seeds: Vec<(Vec2, FoodKind)> = vec![];
+    q_plants.iter().for_each(|(h, t, k)|{
+        if h.health < config.plant_seed_threshold || rng.f32() >= config.plant_seed_rate * clock.dt { return; }
+        let angle = rng.f32() * std::f32::consts::TAU;
+        let dst = rng.f32() * config.plant_seed_distance;
+        seeds.push((t.translation.truncate() + Vec2::new(angle.cos(), angle.sin()) * dst, *k));
+    });
+    if rng.f32() < config.plant_sprout_rate * clock.dt {
+        let kind = pick_plant_kind(&config.food_types, rng.f32());
+        seeds.extend(fertile_spot(&config, &fertility, kind, &mut rng).map(|p| (p, kind)));
+    }
+
+    seeds.into_iter().for_each(|(pos, kind)|{
+        let pos = config.topology.wrap(pos, config.world_bound);
+        if total >= config.plant_cap || !config.food_types[kind.0].grows_at(pos, config.food_area) { return; }
+        let n = crowd.entry(fertility.cell_of(pos)).or_default();
+        if *n as f32 >= config.plant_cell_capacity * fertility.at(pos) { return; }
+        *n += 1;
+        total += 1;
+        spawn_plant(&mut commands, &config, &pos, kind, Plant::default(), config.plant_seed_nutrition);
+    });
+}
+
+// growth and seeding themselves are in fixed system order of MainPlugin
+pub struct PlantPlugin;
+impl Plugin for PlantPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<Fertility>()
+            .add_startup_system(init_plants)
+        ;
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{Minion, Food, Brain, Health, Hunger, Age, Sight, Size, Weapon, MinionState, SimClock, SimRng, WorldConfig, spawn_minion, spawn_food};
+use crate::species::{Species, SpeciesRegistry};
+use crate::traits::Traits;
+use crate::lineage::{Lineage, LineageStore};
+use crate::plants::{Plant, Corpse, Fertility};
+use crate::food::FoodKind;
+use crate::events::Lifetime;
+use crate::hall::HallOfFame;
+
+pub const SNAPSHOT_PATH: &str = "world.dat";
+
+// request to write whole world into given file
+pub struct SaveSnapshot(pub String);
+
+// request to replace whole world with one read from given file
+pub struct LoadSnapshot(pub String);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FoodState{
+    health: Health,
+    position: [f32; 2],
+    kind: FoodKind,
+    plant: Option<Plant>, // corpses have none
+    corpse: Option<Corpse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorldSnapshot{
+    clock: SimClock,
+    rng: SimRng,
+    species: SpeciesRegistry,
+    lineage: LineageStore,
+    fertility: Fertility,
+    hall: HallOfFame,
+    minions: Vec<MinionState>,
+    food: Vec<FoodState>,
+}
+impl WorldSnapshot {
+    fn save(&self, path: &str) -> Result<(), String> {
+        let bytes = bincode::serialize(self).map_err(|e| e.to_string())?;
+        fs::write(path, bytes).map_err(|e| format!("{path}: {e}"))
+    }
+
+    fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+        bincode::deserialize(&bytes).map_err(|e| format!("{path}: {e}"))
+    }
+}
+
+fn save_snapshot(
+    mut events: EventReader<SaveSnapshot>,
+    clock: Res<SimClock>,
+    rng: Res<SimRng>,
+    registry: Res<SpeciesRegistry>,
+    lineage: Res<LineageStore>,
+    fertility: Res<Fertility>,
+    hall: Res<HallOfFame>,
+    q_minions: Query<(&Brain, &Health, &Hunger, &Age, &Sight, &Size, &Transform, &Velocity, &Weapon, Option<&Species>, &Traits, &Lineage, &Lifetime), With<Minion>>,
+    q_food: Query<(&Health, &Transform, Option<&Plant>, &FoodKind, Option<&Corpse>), With<Food>>,
+){
+    for ev in events.iter() {
+        let minions = q_minions.iter().map(|m|{
+            MinionState {
+                brain: m.0.clone(),
+                health: m.1.clone(),
+                hunger: m.2.clone(),
+                age: m.3.clone(),
+                sight: m.4.clone(),
+                size: m.5.clone(),
+                weapon: m.8.clone(),
+                traits: m.10.clone(),
+                lineage: *m.11,
+                lifetime: m.12.clone(),
+                position: m.6.translation.truncate().to_array(),
+                rotation: m.6.rotation.to_euler(EulerRot::XYZ).2,
+                linvel: m.7.linvel.to_array(),
+                angvel: m.7.angvel,
+                species: m.9.copied(),
+            }
+        }).collect();
+
+        let food = q_food.iter().map(|f| FoodState {
+            health: f.0.clone(),
+            position: f.1.translation.truncate().to_array(),
+            kind: *f.3,
+            plant: f.2.cloned(),
+            corpse: f.4.cloned(),
+        }).collect();
+
+        let snapshot = WorldSnapshot { clock: clock.clone(), rng: rng.clone(), species: registry.clone(), lineage: lineage.clone(), fertility: fertility.clone(), hall: hall.clone(), minions, food };
+        match snapshot.save(&ev.0) {
+            Ok(()) => println!("Snapshot saved: {} ({} minions, {} food)", ev.0, snapshot.minions.len(), snapshot.food.len()),
+            Err(e) => println!("ERR: Snapshot not saved: {e}"),
+        }
+    }
+}
+
+// despawns current world and rebuilds it, including physics bodies
+fn load_snapshot(
+    mut commands: Commands,
+    mut events: EventReader<LoadSnapshot>,
+    mut clock: ResMut<SimClock>,
+    mut rng: ResMut<SimRng>,
+    mut registry: ResMut<SpeciesRegistry>,
+    mut lineage: ResMut<LineageStore>,
+    mut fertility: ResMut<Fertility>,
+    mut hall: ResMut<HallOfFame>,
+    config: Res<WorldConfig>,
+    q_existing: Query<Entity, Or<(With<Minion>, With<Food>)>>,
+){
+    for ev in events.iter() {
+        let snapshot = match WorldSnapshot::load(&ev.0) {
+            Ok(s) => s,
+            Err(e) => { println!("ERR: Snapshot not loaded: {e}"); continue; }
+        };
+
+        q_existing.iter().for_each(|e| commands.entity(e).despawn_recursive());
+
+        *clock = snapshot.clock;
+        *rng = snapshot.rng;
+        *registry = snapshot.species;
+        *lineage = snapshot.lineage;
+        *fertility = snapshot.fertility;
+        *hall = snapshot.hall;
+        println!("Snapshot loaded: {} ({} minions, {} food)", ev.0, snapshot.minions.len(), snapshot.food.len());
+
+        snapshot.minions.into_iter().for_each(|m|{ spawn_minion(&mut commands, m); });
+        snapshot.food.into_iter().for_each(|f|{
+            let food = match config.food_types.get(f.kind.0) {
+                Some(t) => t,
+                None => { println!("ERR: Unknown food kind {} in snapshot, skipped", f.kind.0); return; }
+            };
+            let id = spawn_food(&mut commands, &Vec2::from(f.position), f.kind, food, f.health.health);
+            commands.entity(id).insert(f.health);
+            if let Some(p) = f.plant { commands.entity(id).insert(p); }
+            if let Some(c) = f.corpse { commands.entity(id).insert(c); }
+        });
+    }
+}
+
+// periodic saves, so crashed long runs can be resumed
+fn autosave(
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    mut last: Local<f32>,
+    mut ev_save: EventWriter<SaveSnapshot>,
+){
+    if config.snapshot_interval > 0.0 && clock.elapsed - *last >= config.snapshot_interval {
+        *last = clock.elapsed;
+        ev_save.send(SaveSnapshot(config.output(SNAPSHOT_PATH)));
+    }
+}
+
+// file given with `--load path` (or to `replay`) replaces freshly spawned world on first frame
+#[derive(Debug, Default, Resource)]
+pub struct SnapshotOnStart(pub Option<String>);
+fn load_on_start(start: Res<SnapshotOnStart>, mut ev_load: EventWriter<LoadSnapshot>) {
+    if let Some(path) = &start.0 {
+        ev_load.send(LoadSnapshot(path.clone()));
+    }
+}
+
+pub struct SnapshotPlugin;
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .add_event::<SaveSnapshot>()
+            .add_event::<LoadSnapshot>()
+            .init_resource::<SnapshotOnStart>()
+            .add_startup_system(load_on_start)
+            .add_system(autosave)
+            .add_system(save_snapshot.after(autosave))
+            .add_system(load_snapshot.after(save_snapshot))
+        ;
+    }
+}
@@ -0,0 +1,152 @@
+// kept free of other modules of the crate, so benches can include it directly
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Minion,
+    Food(f32), // diet value of food type
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GridItem {
+    pub entity: Entity,
+    pub pos: Vec2,
+    pub radius: f32,
+    pub kind: ItemKind,
+}
+
+// uniform grid of everything minions can see or bite, rebuilt every tick,
+// cells should be about as big as usual query radius
+#[derive(Debug, Default, Resource)]
+pub struct SpatialGrid {
+    cell: f32,
+    wrap: Option<(f32, i32)>, // world size and cells across it, when world is a torus
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    items: Vec<GridItem>,
+    max_radius: f32,
+}
+impl SpatialGrid {
+    // on torus `cell` is shrunk a bit, so whole number of cells fits around the world
+    pub fn new(cell: f32, torus_size: Option<f32>) -> Self {
+        match torus_size {
+            Some(size) => {
+                let n = ((size / cell).ceil() as i32).max(1);
+                Self { cell: size / n as f32, wrap: Some((size, n)), ..default() }
+            }
+            None => Self { cell, ..default() },
+        }
+    }
+
+    pub fn rebuild(&mut self, items: impl Iterator<Item = GridItem>) {
+        self.cells.values_mut().for_each(|c| c.clear());
+        self.items.clear();
+        self.items.extend(items);
+        self.max_radius = self.items.iter().map(|it| it.radius).fold(0.0, f32::max);
+        for (i, it) in self.items.iter().enumerate() {
+            let (x, y) = self.key(it.pos);
+            let key = self.wrap_key(x, y);
+            self.cells.entry(key).or_default().push(i);
+        }
+    }
+
+    // biggest item, queries that care about item size grow their radius by it
+    pub fn max_radius(&self) -> f32 { self.max_radius }
+
+    // torus cells are counted from its corner at -size/2
+    fn key(&self, p: Vec2) -> (i32, i32) {
+        let p = match self.wrap { Some((size, _)) => p + size / 2.0, None => p };
+        ((p.x / self.cell).floor() as i32, (p.y / self.cell).floor() as i32)
+    }
+
+    fn wrap_key(&self, x: i32, y: i32) -> (i32, i32) {
+        match self.wrap {
+            Some((_, n)) => (x.rem_euclid(n), y.rem_euclid(n)),
+            None => (x, y),
+        }
+    }
+
+    // shortest vector from a to b, over the seam on torus
+    pub fn delta(&self, a: Vec2, b: Vec2) -> Vec2 {
+        let d = b - a;
+        match self.wrap {
+            Some((size, _)) => d - (d / size).round() * size,
+            None => d,
+        }
+    }
+
+    // calls `f` with every item whose center is within `radius` of `center`, and vector to it
+    pub fn within(&self, center: Vec2, radius: f32, mut f: impl FnMut(&GridItem, Vec2)) {
+        let (cx, cy) = self.key(center);
+        let r = (radius / self.cell).ceil() as i32;
+        // never visit same torus cell twice when radius is bigger than the world
+        let span = |c: i32| match self.wrap {
+            Some((_, n)) if 2 * r + 1 >= n => 0..n,
+            _ => (c - r)..(c + r + 1),
+        };
+        for x in span(cx) {
+            for y in span(cy) {
+                let idx = match self.cells.get(&self.wrap_key(x, y)) {
+                    Some(idx) => idx,
+                    None => continue,
+                };
+                for i in idx {
+                    let it = &self.items[*i];
+                    let d = self.delta(center, it.pos);
+                    if d.length_squared() <= radius * radius { f(it, d); }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-4;
+
+    fn item(i: u32, x: f32, y: f32) -> GridItem {
+        GridItem { entity: Entity::from_raw(i), pos: Vec2::new(x, y), radius: 1.0, kind: ItemKind::Minion }
+    }
+
+    fn found(grid: &SpatialGrid, center: Vec2, radius: f32) -> Vec<(Entity, Vec2)> {
+        let mut out = vec![];
+        grid.within(center, radius, |it, d| out.push((it.entity, d)));
+        out
+    }
+
+    #[test]
+    fn torus_finds_item_across_seam() {
+        // world spans -100..100
+        let mut grid = SpatialGrid::new(10.0, Some(200.0));
+        grid.rebuild([item(0, -98.0, 0.0), item(1, 50.0, 0.0)].into_iter());
+        let hits = found(&grid, Vec2::new(97.0, 0.0), 8.0);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, Entity::from_raw(0));
+        assert!((hits[0].1 - Vec2::new(5.0, 0.0)).length() < EPS);
+        // next to it on its own side, delta points the other way
+        let hits = found(&grid, Vec2::new(-96.0, 0.0), 8.0);
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].1 - Vec2::new(-2.0, 0.0)).length() < EPS);
+    }
+
+    #[test]
+    fn bounded_grid_does_not_wrap() {
+        let mut grid = SpatialGrid::new(10.0, None);
+        grid.rebuild([item(0, -98.0, 0.0)].into_iter());
+        assert!(found(&grid, Vec2::new(97.0, 0.0), 8.0).is_empty());
+        assert!((grid.delta(Vec2::new(97.0, 0.0), Vec2::new(-98.0, 0.0)) - Vec2::new(-195.0, 0.0)).length() < EPS);
+    }
+
+    #[test]
+    fn radius_spanning_world_returns_each_item_once() {
+        // 4 cells across, radius covers far more than that
+        let mut grid = SpatialGrid::new(10.0, Some(40.0));
+        grid.rebuild([item(0, -19.0, -19.0), item(1, 0.0, 0.0), item(2, 19.0, 5.0)].into_iter());
+        let mut hits: Vec<Entity> = found(&grid, Vec2::new(3.0, 3.0), 100.0).into_iter().map(|(e, _)| e).collect();
+        hits.sort();
+        assert_eq!(hits, vec![Entity::from_raw(0), Entity::from_raw(1), Entity::from_raw(2)]);
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use rusty_neat::NN;
+
+use crate::{Minion, Brain, SimRng, WorldConfig};
+use crate::speed::SimSystems;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Component)]
+pub struct Species{pub id: u32}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SpeciesEntry{
+    id: u32,
+    representative: NN, // genome of first member, new minions are compared to it
+    members: usize,
+}
+
+// all living species, ids are never reused
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Resource)]
+pub struct SpeciesRegistry{
+    next_id: u32,
+    species: Vec<SpeciesEntry>,
+}
+impl SpeciesRegistry {
+    pub fn count(&self) -> usize { self.species.len() }
+
+    // first (oldest) compatible species, or new one with given genome as representative
+    fn assign(&mut self, nn: &NN, config: &WorldConfig) -> Species {
+        if let Some(s) = self.species.iter_mut().find(|s| compatibility(&s.representative, nn, config) < config.species_threshold) {
+            s.members += 1;
+            return Species{id: s.id};
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.species.push(SpeciesEntry{id, representative: nn.clone(), members: 1});
+        Species{id}
+    }
+}
+
+// NEAT-like distance, connections are matched by nodes they join,
+// unmatched ones count as disjoint, matched ones add their weight difference
+pub fn compatibility(a: &NN, b: &NN, config: &WorldConfig) -> f32 {
+    let mut matched = 0;
+    let mut weight_diff = 0_f32;
+    a.connections.iter().for_each(|c|{
+        if let Some(o) = b.connections.iter().find(|o| o.from == c.from && o.to == c.to) {
+            matched += 1;
+            weight_diff += (c.weight - o.weight).abs() as f32;
+        }
+    });
+    let disjoint = a.connections.len() + b.connections.len() - 2 * matched;
+    let n = a.connections.len().max(b.connections.len()).max(1);
+
+    config.compat_disjoint * disjoint as f32 / n as f32 + config.compat_weight * weight_diff / matched.max(1) as f32
+}
+
+// child has structure of fitter parent, weights of shared connections are taken from either at random
+pub fn crossover(fitter: &NN, other: &NN, rng: &mut SimRng) -> NN {
+    let mut child = fitter.clone();
+    child.connections.iter_mut().for_each(|c|{
+        if let Some(o) = other.connections.iter().find(|o| o.from == c.from && o.to == c.to) {
+            if rng.f32() < 0.5 { c.weight = o.weight; }
+            // like in NEAT, gene disabled in any parent is likely to stay disabled
+            if !(c.active && o.active) { c.active = rng.f32() >= 0.75; }
+        }
+    });
+    child
+}
+
+// recounts members and forgets extinct species
+fn u_species(
+    mut registry: ResMut<SpeciesRegistry>,
+    query: Query<&Species, With<Minion>>,
+){
+    registry.species.iter_mut().for_each(|s| s.members = 0);
+    query.iter().for_each(|sp|{
+        if let Some(s) = registry.species.iter_mut().find(|s| s.id == sp.id) { s.members += 1; }
+    });
+    registry.species.retain(|s| s.members > 0);
+}
+
+fn assign_species(
+    mut commands: Commands,
+    config: Res<WorldConfig>,
+    mut registry: ResMut<SpeciesRegistry>,
+    query: Query<(Entity, &Brain), (With<Minion>, Without<Species>)>,
+){
+    query.iter().for_each(|(e, brain)|{
+        let sp = registry.assign(&brain.nn, &config);
+        commands.entity(e).insert(sp);
+    });
+}
+
+pub struct SpeciesPlugin;
+impl Plugin for SpeciesPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<SpeciesRegistry>()
+            .add_sim_system(u_species)
+            .add_sim_system(assign_species.after(u_species))
+        ;
+    }
+}
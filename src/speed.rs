@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use bevy::ecs::schedule::IntoSystemDescriptor;
+use bevy_rapier2d::prelude::*;
+
+pub const MAX_SPEED: u32 = 64;
+
+// how many simulation ticks run per rendered frame
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct SimSpeed {
+    pub paused: bool,
+    pub multiplier: u32, // ticks per frame while running, 1..=MAX_SPEED
+    pub step: bool,      // single tick on next frame, while paused
+}
+impl Default for SimSpeed {
+    fn default() -> Self { Self { paused: false, multiplier: 1, step: false } }
+}
+impl SimSpeed {
+    pub fn ticks(&self) -> u32 {
+        if !self.paused { self.multiplier } else if self.step { 1 } else { 0 }
+    }
+    pub fn faster(&mut self) { self.multiplier = (self.multiplier * 2).min(MAX_SPEED); }
+    pub fn slower(&mut self) { self.multiplier = (self.multiplier / 2).max(1); }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub enum SimStage {
+    Loop, // in app schedule, right after Update
+    Tick, // inside of loop, simulation systems, physics follows them
+}
+
+// whole simulation tick (systems, then rapier step) as its own schedule,
+// repeated as many times per frame as SimSpeed says, so physics always moves by one timestep per tick
+pub struct SimLoop(Schedule);
+impl SimLoop {
+    fn new() -> Self {
+        let physics = |s| SystemStage::parallel().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(s));
+        let mut schedule = Schedule::default();
+        schedule
+            .add_stage(SimStage::Tick, SystemStage::parallel())
+            .add_stage_after(SimStage::Tick, PhysicsStages::SyncBackend, physics(PhysicsStages::SyncBackend))
+            .add_stage_after(PhysicsStages::SyncBackend, PhysicsStages::StepSimulation, physics(PhysicsStages::StepSimulation))
+            .add_stage_after(PhysicsStages::StepSimulation, PhysicsStages::Writeback, physics(PhysicsStages::Writeback))
+        ;
+        Self(schedule)
+    }
+}
+impl Stage for SimLoop {
+    fn run(&mut self, world: &mut World) {
+        let ticks = world.resource::<SimSpeed>().ticks();
+        world.resource_mut::<SimSpeed>().step = false;
+        for _ in 0..ticks { self.0.run_once(world); }
+    }
+}
+
+// systems changing the world go through this instead of add_system, so they pause and speed up with it
+pub trait SimSystems {
+    fn add_sim_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self;
+}
+impl SimSystems for App {
+    fn add_sim_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self {
+        self.stage(SimStage::Loop, |sim: &mut SimLoop| {
+            sim.0.add_system_to_stage(SimStage::Tick, system);
+            sim
+        })
+    }
+}
+
+// P pauses, . steps single tick while paused, + / - doubles or halves speed
+pub fn speed_keys(keys: Res<Input<KeyCode>>, mut speed: ResMut<SimSpeed>) {
+    if keys.just_pressed(KeyCode::P) { speed.paused = !speed.paused; }
+    if keys.just_pressed(KeyCode::Period) && speed.paused { speed.step = true; }
+    if keys.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) { speed.faster(); }
+    if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) { speed.slower(); }
+}
+
+// has to be added before any plugin calling add_sim_system,
+// rapier plugin itself goes without its default stages
+pub struct SpeedPlugin;
+impl Plugin for SpeedPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<SimSpeed>()
+            .add_stage_after(CoreStage::Update, SimStage::Loop, SimLoop::new())
+            // despawns are caught once more at the end of frame, like rapier does by default
+            .add_stage_before(CoreStage::Last, PhysicsStages::DetectDespawn, SystemStage::parallel()
+                .with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::DetectDespawn)))
+        ;
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fs::File, io::{BufWriter, Write}};
+
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{Minion, Food, Brain, Age, Hunger, Health, SimClock, WorldConfig, reproduce, mate, u_hp};
+use crate::species::SpeciesRegistry;
+use crate::events::{MinionDied, MinionBorn, DeathCause};
+use crate::speed::SimSystems;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsFormat {
+    Csv,
+    JsonLines,
+}
+
+// events since last written row
+#[derive(Debug, Default, Resource)]
+pub struct StatsCounters {
+    pub births: u64,
+    pub starvation: u64,
+    pub killed: u64,
+    pub old_age: u64,
+    pub interventions: u64, // times extinction policy kicked in
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StatsRow {
+    tick: u64,
+    time: f32,
+    population: usize,
+    food: usize,
+    mean_age: f32,
+    max_age: f32,
+    mean_generation: f64,
+    mean_hunger: f32,
+    mean_health: f32,
+    births: u64,
+    deaths_starvation: u64,
+    deaths_killed: u64,
+    deaths_old_age: u64,
+    species: usize,
+    interventions: u64,
+}
+impl StatsRow {
+    const CSV_HEADER: &'static str = "tick,time,population,food,mean_age,max_age,mean_generation,mean_hunger,mean_health,births,deaths_starvation,deaths_killed,deaths_old_age,species,interventions";
+
+    fn csv(&self) -> String {
+        format!("{},{:.3},{},{},{:.3},{:.3},{:.3},{:.4},{:.4},{},{},{},{},{},{}",
+            self.tick, self.time, self.population, self.food, self.mean_age, self.max_age, self.mean_generation,
+            self.mean_hunger, self.mean_health, self.births, self.deaths_starvation, self.deaths_killed, self.deaths_old_age, self.species, self.interventions)
+    }
+}
+
+#[derive(Default, Resource)]
+struct StatsRecorder {
+    out: Option<BufWriter<File>>,
+    last: f32,
+}
+
+fn open_stats(config: Res<WorldConfig>, mut recorder: ResMut<StatsRecorder>) {
+    let path = match &config.stats_path { Some(p) => config.output(p), None => return };
+    match File::create(&path) {
+        Ok(f) => {
+            let mut out = BufWriter::new(f);
+            if config.stats_format == StatsFormat::Csv {
+                writeln!(out, "{}", StatsRow::CSV_HEADER).ok();
+            }
+            recorder.out = Some(out);
+            println!("Writing stats to: {path}");
+        }
+        Err(e) => println!("ERR: Stats file not created: {path}: {e}"),
+    }
+}
+
+// founders are born too, only children count as births
+fn count_births(mut events: EventReader<MinionBorn>, mut counters: ResMut<StatsCounters>) {
+    counters.births += events.iter().filter(|ev| ev.lineage.parent.is_some()).count() as u64;
+}
+
+// deaths arrive as events, tallied every frame so none is missed between rows
+fn count_deaths(mut events: EventReader<MinionDied>, mut counters: ResMut<StatsCounters>) {
+    events.iter().for_each(|ev|{
+        match ev.cause {
+            DeathCause::Starvation => counters.starvation += 1,
+            DeathCause::Predation(_) => counters.killed += 1,
+            DeathCause::OldAge => counters.old_age += 1,
+        }
+    });
+}
+
+fn record_stats(
+    clock: Res<SimClock>,
+    config: Res<WorldConfig>,
+    registry: Res<SpeciesRegistry>,
+    mut recorder: ResMut<StatsRecorder>,
+    mut counters: ResMut<StatsCounters>,
+    q_minions: Query<(&Age, &Brain, &Hunger, &Health), With<Minion>>,
+    q_food: Query<&Food>,
+){
+    if recorder.out.is_none() || clock.elapsed - recorder.last < config.stats_interval { return; }
+    recorder.last = clock.elapsed;
+
+    let population = q_minions.iter().len();
+    let n = population.max(1) as f32;
+    let mut row = StatsRow {
+        tick: clock.tick,
+        time: clock.elapsed,
+        population,
+        food: q_food.iter().len(),
+        mean_age: 0.0,
+        max_age: 0.0,
+        mean_generation: 0.0,
+        mean_hunger: 0.0,
+        mean_health: 0.0,
+        births: counters.births,
+        deaths_starvation: counters.starvation,
+        deaths_killed: counters.killed,
+        deaths_old_age: counters.old_age,
+        species: registry.count(),
+        interventions: counters.interventions,
+    };
+    q_minions.iter().for_each(|(age, brain, hunger, health)|{
+        row.mean_age += age.age / n;
+        row.max_age = row.max_age.max(age.age);
+        row.mean_generation += brain.nn.generation as f64 / n as f64;
+        row.mean_hunger += hunger.filled / n;
+        row.mean_health += health.health / n;
+    });
+    *counters = StatsCounters::default();
+
+    let line = match config.stats_format {
+        StatsFormat::Csv => row.csv(),
+        StatsFormat::JsonLines => serde_json::to_string(&row).unwrap(),
+    };
+    if let Some(out) = recorder.out.as_mut() {
+        // flushed every row, so nothing is lost when run is killed
+        if let Err(e) = writeln!(out, "{line}").and_then(|_| out.flush()) {
+            println!("ERR: Stats not written: {e}");
+        }
+    }
+}
+
+pub struct StatsPlugin;
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<StatsCounters>()
+            .init_resource::<StatsRecorder>()
+            .add_startup_system(open_stats)
+            // events are counted in the tick they were sent
+            .add_sim_system(count_births.after(reproduce).after(mate))
+            .add_sim_system(count_deaths.after(u_hp))
+            .add_sim_system(record_stats.after(count_births).after(count_deaths))
+        ;
+    }
+}
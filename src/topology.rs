@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{Minion, Food, WorldConfig};
+
+// shape of the world inside ±world_bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Topology {
+    // leaving one side brings you back on the other, and things see and bite across it
+    #[default]
+    Torus,
+    // closed by walls along the bound
+    Bounded,
+}
+impl Topology {
+    // shortest vector from a to b, over the seam when it is shorter that way
+    pub fn delta(&self, a: Vec2, b: Vec2, bound: f32) -> Vec2 {
+        let d = b - a;
+        match self {
+            Topology::Bounded => d,
+            Topology::Torus => {
+                let size = 2.0 * bound;
+                d - (d / size).round() * size
+            }
+        }
+    }
+
+    pub fn wrap(&self, p: Vec2, bound: f32) -> Vec2 {
+        match self {
+            Topology::Bounded => p,
+            Topology::Torus => {
+                let size = 2.0 * bound;
+                Vec2::new((p.x + bound).rem_euclid(size) - bound, (p.y + bound).rem_euclid(size) - bound)
+            }
+        }
+    }
+
+    // point moved back into the world, body of `radius` stays clear of border walls
+    pub fn confine(&self, p: Vec2, bound: f32, radius: f32) -> Vec2 {
+        match self {
+            Topology::Torus => self.wrap(p, bound),
+            Topology::Bounded => {
+                let b = (bound - radius).max(0.0);
+                p.clamp(Vec2::splat(-b), Vec2::splat(b))
+            }
+        }
+    }
+}
+
+// torus only, anything pushed over the bound comes back on the other side
+pub fn wrap_bodies(
+    config: Res<WorldConfig>,
+    mut query: Query<&mut Transform, Or<(With<Minion>, With<Food>)>>,
+){
+    if config.topology != Topology::Torus { return; }
+    query.par_for_each_mut(16, |mut t|{
+        let p = t.translation.truncate();
+        let w = config.topology.wrap(p, config.world_bound);
+        if w != p {
+            t.translation.x = w.x;
+            t.translation.y = w.y;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-4;
+
+    #[test]
+    fn torus_delta_goes_over_seam() {
+        let t = Topology::Torus;
+        let d = t.delta(Vec2::new(95.0, 0.0), Vec2::new(-95.0, 0.0), 100.0);
+        assert!((d - Vec2::new(10.0, 0.0)).length() < EPS);
+        let d = t.delta(Vec2::new(-95.0, -98.0), Vec2::new(95.0, 98.0), 100.0);
+        assert!((d - Vec2::new(-10.0, -4.0)).length() < EPS);
+        // shorter inside, so no wrapping
+        let d = t.delta(Vec2::new(-20.0, 0.0), Vec2::new(30.0, 0.0), 100.0);
+        assert!((d - Vec2::new(50.0, 0.0)).length() < EPS);
+    }
+
+    #[test]
+    fn bounded_delta_is_plain_difference() {
+        let d = Topology::Bounded.delta(Vec2::new(95.0, 0.0), Vec2::new(-95.0, 0.0), 100.0);
+        assert!((d - Vec2::new(-190.0, 0.0)).length() < EPS);
+    }
+
+    #[test]
+    fn wrap_brings_back_on_other_side() {
+        let w = Topology::Torus.wrap(Vec2::new(105.0, -103.0), 100.0);
+        assert!((w - Vec2::new(-95.0, 97.0)).length() < EPS);
+        assert_eq!(Topology::Torus.wrap(Vec2::new(10.0, -10.0), 100.0), Vec2::new(10.0, -10.0));
+        assert_eq!(Topology::Bounded.wrap(Vec2::new(105.0, 0.0), 100.0), Vec2::new(105.0, 0.0));
+    }
+
+    #[test]
+    fn bounded_confine_keeps_body_inside() {
+        let c = Topology::Bounded.confine(Vec2::new(450.0, -20.0), 100.0, 10.0);
+        assert_eq!(c, Vec2::new(90.0, -20.0));
+        let c = Topology::Torus.confine(Vec2::new(150.0, 0.0), 100.0, 10.0);
+        assert!((c - Vec2::new(-50.0, 0.0)).length() < EPS);
+    }
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::SimRng;
+
+const GENE_MIN: f32 = 0.25;
+const GENE_MAX: f32 = 4.0;
+
+// hunger per second for every gene point above 1.0, bigger body parts cost more to keep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraitCosts {
+    pub sight: f32,
+    pub size: f32,
+    pub damage: f32,
+    pub reach: f32,
+    pub armour: f32,
+    pub lifespan: f32,
+}
+impl Default for TraitCosts {
+    fn default() -> Self {
+        Self { sight: 0.02, size: 0.02, damage: 0.02, reach: 0.01, armour: 0.02, lifespan: 0.01 }
+    }
+}
+
+// heritable body genome, every gene multiplies default value of component it drives
+#[derive(Debug, Clone, Serialize, Deserialize, Component)]
+pub struct Traits {
+    pub sight: f32,      // Sight.radius
+    pub size: f32,       // Size.radius
+    pub damage: f32,     // Weapon.damage
+    pub reach: f32,      // Weapon.width
+    pub armour: f32,     // Health.protection is divided by it
+    pub metabolism: f32, // Hunger.metabolism and max force, faster but hungrier
+    pub lifespan: f32,   // Age.lifespan
+    pub diet: f32,       // food with closest FoodType.diet is digested best
+}
+impl Default for Traits {
+    fn default() -> Self {
+        Self { sight: 1.0, size: 1.0, damage: 1.0, reach: 1.0, armour: 1.0, metabolism: 1.0, lifespan: 1.0, diet: 1.0 }
+    }
+}
+impl Traits {
+    fn genes_mut(&mut self) -> [&mut f32; 8] {
+        [&mut self.sight, &mut self.size, &mut self.damage, &mut self.reach, &mut self.armour, &mut self.metabolism, &mut self.lifespan, &mut self.diet]
+    }
+
+    // copy for a newborn, every gene has `rate` chance to be scaled by up to ±`size`
+    pub fn offspring(&self, rate: f32, size: f32, rng: &mut SimRng) -> Self {
+        let mut t = self.clone();
+        t.genes_mut().into_iter().for_each(|g|{
+            if rng.f32() < rate {
+                *g = (*g * (1.0 + (rng.f32() - 0.5) * 2.0 * size)).clamp(GENE_MIN, GENE_MAX);
+            }
+        });
+        t
+    }
+
+    // every gene taken from either parent at random
+    pub fn crossed(&self, other: &Traits, rng: &mut SimRng) -> Self {
+        let mut t = self.clone();
+        let mut o = other.clone();
+        t.genes_mut().into_iter().zip(o.genes_mut()).for_each(|(g, og)|{
+            if rng.f32() < 0.5 { *g = *og; }
+        });
+        t
+    }
+
+    // extra hunger burned every second just for having this body
+    pub fn upkeep(&self, costs: &TraitCosts) -> f32 {
+        let over = |g: f32| (g - 1.0).max(0.0);
+        costs.sight * over(self.sight)
+            + costs.size * over(self.size)
+            + costs.damage * over(self.damage)
+            + costs.reach * over(self.reach)
+            + costs.armour * over(self.armour)
+            + costs.lifespan * over(self.lifespan)
+    }
+}
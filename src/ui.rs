@@ -6,6 +6,19 @@ use bevy_pixel_camera::PixelCameraPlugin;
 use rusty_neat::{NN, ActFunc};
 use simplesvg as svg;
 
+use std::collections::{HashMap, HashSet};
+
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+
+use crate::{Minion, SimClock, WorldConfig};
+use crate::speed::{SimSpeed, MAX_SPEED};
+use crate::extinction::ExtinctionLog;
+use crate::hall::{HallOfFame, SpawnFromHall};
+use crate::events::Lifetime;
+use crate::topology::Topology;
+use crate::species::SpeciesRegistry;
+use crate::lineage::{Lineage, LineageId, LineageNode, LineageStore};
+
 use bevy_egui::EguiSettings;
 
 fn update_ui_scale_factor(mut egui_settings: ResMut<EguiSettings>, windows: Res<Windows>) {
@@ -24,6 +37,9 @@ pub struct SelectedNN{
     pub hp: f32,
     pub hunger: f32,
     pub age: f32,
+    pub species: Option<u32>,
+    pub lineage: LineageId,
+    pub lifetime: Lifetime,
     pub eid: Entity,
     pub eid_old: Entity
 }
@@ -37,6 +53,9 @@ impl Default for SelectedNN{
             hp: 0.0,
             hunger: 0.0,
             age: 0.0,
+            species: None,
+            lineage: LineageId(u64::MAX),
+            lifetime: Lifetime::default(),
             eid: Entity::from_raw(0),
             eid_old: Entity::from_raw(1)
         } 
@@ -157,6 +176,10 @@ fn ui_window(
     diagnostics: Res<Diagnostics>, 
     plot_p: Res<PlotPop>,
     w_p: Res<CursorWorld>,
+    species: Res<SpeciesRegistry>,
+    clock: Res<SimClock>,
+    mut speed: ResMut<SimSpeed>,
+    extinctions: Res<ExtinctionLog>,
 ) {
     let texture = h_texture
         .egui_texture_handle
@@ -183,6 +206,24 @@ fn ui_window(
 
         let v = time.elapsed_seconds();
         ui.label(format!("Elapsed: \n{v:.0}s"));
+        ui.label(format!("Simulated: \n{:.0}s, tick {}", clock.elapsed, clock.tick));
+
+        // P pause, . step, + / - speed
+        ui.horizontal(|ui|{
+            let label = if speed.paused { "Run" } else { "Pause" };
+            if ui.button(label).clicked() { speed.paused = !speed.paused; }
+            if ui.add_enabled(speed.paused, egui::Button::new("Step")).clicked() { speed.step = true; }
+        });
+        ui.horizontal(|ui|{
+            if ui.button("-").clicked() { speed.slower(); }
+            ui.label(if speed.paused { "paused".to_string() } else { format!("{}x", speed.multiplier) });
+            if ui.add_enabled(speed.multiplier < MAX_SPEED, egui::Button::new("+")).clicked() { speed.faster(); }
+        });
+        ui.label(format!("Species: {}", species.count()));
+        if let Some(last) = extinctions.interventions.last() {
+            ui.label(format!("Extinctions: {}", extinctions.interventions.len()));
+            ui.label(format!("- last at {:.0}s, {} left, {:?} +{}", last.time, last.population, last.policy, last.added));
+        }
 
         ui.label(format!("Camera mode: "));
         if w_p.follow {
@@ -196,10 +237,16 @@ fn ui_window(
         ui.heading("Best");
         ui.label(format!("Age: {:.0}", sel.age));
         ui.label(format!("Generation: {:.0}", sel.nn.generation));
+        if let Some(s) = sel.species { ui.label(format!("Species: {s}")); }
         ui.label("HP:");
         ui.add(egui::widgets::ProgressBar::new(sel.hp));
         ui.label("Hunger:");
         ui.add(egui::widgets::ProgressBar::new(sel.hunger/2.0));
+        let life = &sel.lifetime;
+        ui.label(format!("Food eaten: {:.1}, energy spent: {:.1}", life.food_eaten, life.energy_spent));
+        ui.label(format!("Damage dealt: {:.2}, taken: {:.2}", life.damage_dealt, life.damage_taken));
+        ui.label(format!("Kills: {}, children: {}", life.kills, life.offspring));
+        ui.label(format!("Distance: {:.0}", life.distance));
 
         ui.add(egui::widgets::Image::new(
             texture.id(),
@@ -222,6 +269,129 @@ fn ui_window(
 }
 
 
+// what branches of lineage tree are coloured by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum TreeColour {
+    #[default]
+    Species,
+    Sight,
+    Size,
+    Damage,
+    Armour,
+    Metabolism,
+    Lifespan,
+    Diet,
+}
+impl TreeColour {
+    const ALL: [TreeColour; 8] = [Self::Species, Self::Sight, Self::Size, Self::Damage, Self::Armour, Self::Metabolism, Self::Lifespan, Self::Diet];
+
+    fn colour(&self, n: &LineageNode) -> egui::Color32 {
+        const PALETTE: [egui::Color32; 8] = [
+            egui::Color32::from_rgb(230, 25, 75), egui::Color32::from_rgb(60, 180, 75),
+            egui::Color32::from_rgb(255, 225, 25), egui::Color32::from_rgb(0, 130, 200),
+            egui::Color32::from_rgb(245, 130, 48), egui::Color32::from_rgb(145, 30, 180),
+            egui::Color32::from_rgb(70, 240, 240), egui::Color32::from_rgb(240, 50, 230),
+        ];
+        let gene = match self {
+            Self::Species => return n.species.map_or(egui::Color32::GRAY, |s| PALETTE[s as usize % PALETTE.len()]),
+            Self::Sight => n.traits.sight,
+            Self::Size => n.traits.size,
+            Self::Damage => n.traits.damage,
+            Self::Armour => n.traits.armour,
+            Self::Metabolism => n.traits.metabolism,
+            Self::Lifespan => n.traits.lifespan,
+            Self::Diet => n.traits.diet,
+        };
+        // genes span 0.25..4.0, blue for smallest, red for biggest
+        let t = ((gene.ln() - 0.25_f32.ln()) / 16_f32.ln()).clamp(0.0, 1.0);
+        egui::Color32::from_rgb((t * 255.0) as u8, 64, ((1.0 - t) * 255.0) as u8)
+    }
+}
+
+// ancestry of everyone alive, time goes left to right,
+// clicking a branch selects living minion from it and follows it with camera
+fn ui_tree(
+    clock: Res<SimClock>,
+    store: Res<LineageStore>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut sel: ResMut<SelectedNN>,
+    mut w_p: ResMut<CursorWorld>,
+    mut colour: Local<TreeColour>,
+    q_minions: Query<(Entity, &Lineage), With<Minion>>,
+){
+    let entities: HashMap<LineageId, Entity> = q_minions.iter().map(|(e, l)| (l.id, e)).collect();
+    let living: HashSet<LineageId> = entities.keys().copied().collect();
+
+    egui::Window::new("Lineage").default_open(false).show(egui_ctx.ctx_mut(), |ui|{
+        ui.horizontal_wrapped(|ui|{
+            TreeColour::ALL.iter().for_each(|c|{ ui.selectable_value(&mut *colour, *c, format!("{c:?}")); });
+        });
+
+        let tree = store.living_tree(&living, clock.elapsed);
+        let (response, painter) = ui.allocate_painter(egui::Vec2::new(400.0, 300.0), egui::Sense::click());
+        let rect = response.rect;
+        let t0 = tree.iter().map(|b| b.start).fold(clock.elapsed, f32::min);
+        let rows = tree.iter().map(|b| b.row).fold(0.0, f32::max).max(1.0);
+        let x = |t: f32| rect.left() + (t - t0) / (clock.elapsed - t0).max(1.0) * rect.width();
+        let y = |r: f32| rect.top() + r / rows * rect.height();
+
+        tree.iter().for_each(|b|{
+            let c = store.get(b.id).map_or(egui::Color32::GRAY, |n| colour.colour(n));
+            let width = if b.alive { 1.5 } else { 1.0 };
+            painter.line_segment([egui::pos2(x(b.start), y(b.row)), egui::pos2(x(b.end), y(b.row))], egui::Stroke::new(width, c));
+            if let Some(p) = b.parent {
+                painter.line_segment([egui::pos2(x(b.start), y(tree[p].row)), egui::pos2(x(b.start), y(b.row))], egui::Stroke::new(1.0, c));
+            }
+            if b.id == sel.lineage { 
+                painter.circle_filled(egui::pos2(x(b.end), y(b.row)), 3.0, egui::Color32::WHITE); 
+            }
+        });
+
+        // nearest branch under pointer, horizontally within its lifetime
+        if let Some(pos) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+            let hit = tree.iter()
+                .filter(|b| pos.x >= x(b.start) - 2.0 && pos.x <= x(b.end) + 2.0)
+                .map(|b| (b, (y(b.row) - pos.y).abs()))
+                .filter(|(_, d)| *d < 6.0)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some(e) = hit.and_then(|(b, _)| entities.get(&b.descendant)) {
+                sel.eid = *e;
+                w_p.follow = true;
+            }
+        }
+        ui.label(format!("Alive: {}, shown branches: {}", living.len(), tree.len()));
+    });
+}
+
+// best minions so far, each can be brought back as mutated copies
+fn ui_hall(
+    hall: Res<HallOfFame>,
+    mut egui_ctx: ResMut<EguiContext>,
+    mut count: Local<usize>,
+    mut ev_spawn: EventWriter<SpawnFromHall>,
+){
+    egui::Window::new("Hall of fame").default_open(false).show(egui_ctx.ctx_mut(), |ui|{
+        *count = (*count).max(1);
+        ui.add(egui::Slider::new(&mut *count, 1..=50).text("copies"));
+        if hall.entries.is_empty() { ui.label("Nobody died yet"); }
+        egui::Grid::new("hall").striped(true).show(ui, |ui|{
+            ["#", "fitness", "age", "children", "kills", "food", "gen", ""].iter().for_each(|h|{ ui.label(*h); });
+            ui.end_row();
+            hall.entries.iter().enumerate().for_each(|(i, e)|{
+                ui.label(format!("{i}"));
+                ui.label(format!("{:.1}", e.fitness));
+                ui.label(format!("{:.0}s", e.age));
+                ui.label(format!("{}", e.lifetime.offspring));
+                ui.label(format!("{}", e.lifetime.kills));
+                ui.label(format!("{:.1}", e.lifetime.food_eaten));
+                ui.label(format!("{}", e.generation)).on_hover_text(format!("lineage {}", e.lineage.0));
+                if ui.button("Spawn").clicked() { ev_spawn.send(SpawnFromHall { rank: i, count: *count }); }
+                ui.end_row();
+            });
+        });
+    });
+}
+
 pub struct UiManPlugin;
 impl Plugin for UiManPlugin {
     fn build(&self, app: &mut App){
@@ -231,13 +401,15 @@ impl Plugin for UiManPlugin {
             .add_system(update_ui_scale_factor)
             .add_startup_system(setup_ui)
             .add_system(update_cam)
+            .add_system(u_seam_cams.after(update_cam))
             .add_system(cursor_system)
             .add_system(ui_window)
+            .add_system(ui_tree)
+            .add_system(ui_hall)
             .init_resource::<ImageData>()
             .add_system(u_plot)
             .add_system(u_img)
             .insert_resource(PlotTimer(Timer::from_seconds(2.0, TimerMode::Repeating)))
-            .insert_resource(MinionAmount(0))
             .insert_resource(SelectedNN::default())
             .insert_resource(PlotPop {pp: vec![[0.0, 0.0]]})
             .insert_resource(CursorWorld::default())
@@ -248,6 +420,10 @@ impl Plugin for UiManPlugin {
 #[derive(Component)]
 struct MainCamera;
 
+// copy of main camera moved by one world size, draws what is over the seam of torus
+#[derive(Component)]
+struct SeamCamera(Vec2);
+
 #[derive(Default, Debug, Resource)]
 pub struct CursorWorld{
     pub x: f32,
@@ -259,7 +435,8 @@ pub struct CursorWorld{
 // or moving with mouse scrolls (like in fusion360)
 fn update_cam( 
     time: Res<Time>,
-    mut query: Query<&mut Transform, With<Camera2d>>, 
+    config: Res<WorldConfig>,
+    mut query: Query<&mut Transform, With<MainCamera>>, 
     keyboard_input: Res<Input<KeyCode>>,
     mut scroll_evr: EventReader<MouseWheel>,
     //mut motion_evr: EventReader<MouseMotion>,
@@ -282,15 +459,24 @@ fn update_cam(
     }
     let c = cam.scale;
     if keyboard_input.just_pressed(KeyCode::Space) {w_p.follow = !w_p.follow;}
+    let b = config.world_bound;
     if w_p.follow {
-        let v = cam.translation.truncate().lerp(sel.pos, time.delta_seconds()*3.0);
+        // towards selected minion the short way, even when it's over the seam
+        let pos = cam.translation.truncate();
+        let target = pos + config.topology.delta(pos, sel.pos, b);
+        let v = pos.lerp(target, time.delta_seconds()*3.0);
         cam.translation.x = v.x;
         cam.translation.y = v.y;
     }
     else {
         cam.translation += mov;
     }
-    cam.translation = cam.translation.clamp(Vec3::new(-5000.0, -5000.0, -10.0), Vec3::new(5000.0, 5000.0, 10.0));
+    let v = match config.topology {
+        Topology::Torus => config.topology.wrap(cam.translation.truncate(), b),
+        Topology::Bounded => cam.translation.truncate().clamp(Vec2::splat(-b), Vec2::splat(b)),
+    };
+    cam.translation.x = v.x;
+    cam.translation.y = v.y;
     cam.scale += c * Vec3::new(sc, sc, 0.0);
 }
 
@@ -326,10 +512,36 @@ fn cursor_system(
 }
 
 
-fn setup_ui(mut commands: Commands) {
+fn setup_ui(mut commands: Commands, config: Res<WorldConfig>) {
     //commands.spawn(Camera2dBundle::default());
     commands.spawn(Camera2dBundle::default())
         .insert(MainCamera)
     ;
+
+    // on torus eight more cameras draw over main one, each shifted by world size,
+    // so near the seam other side of the world is visible where it belongs
+    if config.topology != Topology::Torus { return; }
+    let size = 2.0 * config.world_bound;
+    let shifts = [(-1.0, -1.0), (0.0, -1.0), (1.0, -1.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0)];
+    shifts.iter().enumerate().for_each(|(i, (x, y))|{
+        commands.spawn(Camera2dBundle {
+            camera: Camera { priority: i as isize + 1, ..default() },
+            camera_2d: Camera2d { clear_color: ClearColorConfig::None },
+            ..default()
+        })
+        .insert(SeamCamera(Vec2::new(*x, *y) * size));
+    });
+}
+
+fn u_seam_cams(
+    q_main: Query<&Transform, With<MainCamera>>,
+    mut q_seam: Query<(&mut Transform, &SeamCamera), Without<MainCamera>>,
+){
+    if let Ok(main) = q_main.get_single() {
+        q_seam.iter_mut().for_each(|(mut t, s)|{
+            *t = *main;
+            t.translation += s.0.extend(0.0);
+        });
+    }
 }
 
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+// one angular slice of field of view, keeps only nearest things seen in it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sector{
+    pub minion: f32, // distance to nearest minion
+    pub food: f32, // distance to nearest food
+    pub food_type: f32, // diet value of nearest food's type
+    pub wall: f32, // distance to nearest wall
+    pub heading: f32, // bearing to nearest of both
+    nearest: f32,
+}
+impl Default for Sector {
+    fn default() -> Self {Self { minion: f32::MAX, food: f32::MAX, food_type: 0.0, wall: f32::MAX, heading: 0.0, nearest: f32::MAX }}
+}
+impl Sector {
+    pub fn see_minion(&mut self, bearing: f32, dst: f32) {
+        self.minion = self.minion.min(dst);
+        self.see(bearing, dst);
+    }
+    pub fn see_food(&mut self, bearing: f32, dst: f32, food_type: f32) {
+        if dst < self.food { self.food = dst; self.food_type = food_type; }
+        self.see(bearing, dst);
+    }
+    pub fn see_wall(&mut self, bearing: f32, dst: f32) {
+        self.wall = self.wall.min(dst);
+        self.see(bearing, dst);
+    }
+    fn see(&mut self, bearing: f32, dst: f32) {
+        if dst < self.nearest { self.nearest = dst; self.heading = bearing; }
+    }
+}
+
+// sector covering given bearing, bearings span from -1.0 (left edge) to 1.0 (right edge) of cone
+pub fn sector_of(sight: &mut [Sector], bearing: f32) -> Option<&mut Sector> {
+    let n = sight.len();
+    if n == 0 { return None; }
+    let idx = ((bearing + 1.0) / 2.0 * n as f32).floor().clamp(0.0, n as f32 - 1.0) as usize;
+    sight.get_mut(idx)
+}
+
+// signed angle (radians) from heading to target, negative on the left, positive on the right
+pub fn bearing(forward: Vec2, diff: Vec2) -> f32 {
+    let right = Vec2::new(forward.y, -forward.x);
+    diff.dot(right).atan2(diff.dot(forward))
+}
+
+// bearing scaled to -1..1 over the cone, None when target is outside of it or behind
+pub fn in_cone(forward: Vec2, diff: Vec2, half_angle: f32, range: f32) -> Option<f32> {
+    if diff.length() > range { return None; }
+    let b = bearing(forward, diff);
+    if b.abs() > half_angle { return None; }
+    Some(b / half_angle)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{PI, FRAC_PI_2, FRAC_PI_4};
+
+    const EPS: f32 = 1e-5;
+
+    #[test]
+    fn bearing_is_signed() {
+        let up = Vec2::new(0.0, 1.0);
+        assert!(bearing(up, Vec2::new(0.0, 10.0)).abs() < EPS);
+        assert!((bearing(up, Vec2::new(10.0, 0.0)) - FRAC_PI_2).abs() < EPS);
+        assert!((bearing(up, Vec2::new(-10.0, 0.0)) + FRAC_PI_2).abs() < EPS);
+        assert!((bearing(up, Vec2::new(5.0, 5.0)) - FRAC_PI_4).abs() < EPS);
+        assert!((bearing(up, Vec2::new(0.0, -10.0)).abs() - PI).abs() < EPS);
+    }
+
+    #[test]
+    fn bearing_follows_rotation() {
+        // facing +x, so -y is on the right
+        let fwd = Vec2::new(1.0, 0.0);
+        assert!((bearing(fwd, Vec2::new(0.0, -3.0)) - FRAC_PI_2).abs() < EPS);
+        assert!((bearing(fwd, Vec2::new(0.0, 3.0)) + FRAC_PI_2).abs() < EPS);
+    }
+
+    #[test]
+    fn cone_filters_sides_behind_and_range() {
+        let up = Vec2::new(0.0, 1.0);
+        let half = FRAC_PI_4;
+        assert_eq!(in_cone(up, Vec2::new(0.0, 50.0), half, 100.0), Some(0.0));
+        assert!(in_cone(up, Vec2::new(0.0, 150.0), half, 100.0).is_none());
+        assert!(in_cone(up, Vec2::new(0.0, -50.0), half, 100.0).is_none());
+        assert!(in_cone(up, Vec2::new(50.0, 10.0), half, 100.0).is_none());
+
+        let right = in_cone(up, Vec2::new(10.0, 50.0), half, 100.0).unwrap();
+        let left = in_cone(up, Vec2::new(-10.0, 50.0), half, 100.0).unwrap();
+        assert!(right > 0.0 && right < 1.0);
+        assert!((left + right).abs() < EPS);
+    }
+
+    #[test]
+    fn sectors_cover_cone_left_to_right() {
+        let mut sight = vec![Sector::default(); 4];
+        let idx = |s: &mut [Sector], b: f32| {
+            let p = sector_of(s, b).unwrap() as *const Sector;
+            s.iter().position(|x| std::ptr::eq(x, p)).unwrap()
+        };
+        assert_eq!(idx(&mut sight, -1.0), 0);
+        assert_eq!(idx(&mut sight, -0.3), 1);
+        assert_eq!(idx(&mut sight, 0.3), 2);
+        assert_eq!(idx(&mut sight, 1.0), 3);
+        assert!(sector_of(&mut [], 0.0).is_none());
+    }
+
+    #[test]
+    fn nearest_object_sets_heading() {
+        let mut s = Sector::default();
+        s.see_food(0.5, 10.0, 2.0);
+        s.see_food(0.1, 15.0, 0.5);
+        s.see_minion(-0.5, 20.0);
+        assert_eq!(s.food, 10.0);
+        assert_eq!(s.food_type, 2.0);
+        assert_eq!(s.minion, 20.0);
+        assert_eq!(s.heading, 0.5);
+        s.see_minion(-0.2, 5.0);
+        assert_eq!(s.heading, -0.2);
+        s.see_wall(0.9, 2.0);
+        assert_eq!(s.wall, 2.0);
+        assert_eq!(s.heading, 0.9);
+    }
+}